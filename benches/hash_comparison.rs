@@ -1,17 +1,5 @@
-use criterion::{criterion_group, criterion_main, Criterion, BenchmarkId};
-use fast_shard::{FastShard, ShardConfig, ShardTier, ShardAlgorithm};
-
-fn create_single_algo_config(algo: ShardAlgorithm) -> ShardConfig {
-    ShardConfig {
-        tiers: vec![
-            ShardTier {
-                size_range: 0..=usize::MAX,
-                algorithms: vec![algo.clone()],
-            },
-        ],
-        default_algorithms: vec![algo],
-    }
-}
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use fast_shard::{FastShard, ShardConfig, ShardAlgorithm};
 
 pub fn bench_hash_algorithms(c: &mut Criterion) {
     let test_sizes = vec![
@@ -29,8 +17,12 @@ pub fn bench_hash_algorithms(c: &mut Criterion) {
         ("AVX512", ShardAlgorithm::Avx512),
         ("AVX2", ShardAlgorithm::Avx2),
         ("AES-NI", ShardAlgorithm::AesNi),
+        ("NEON", ShardAlgorithm::Neon),
+        ("CRC32C", ShardAlgorithm::Crc32c),
         ("XXH3", ShardAlgorithm::Xxh3),
         ("FNV1a", ShardAlgorithm::Fnv1a),
+        // Falls back to XXH3 unless built with `--features wyhash`.
+        ("Wyhash", ShardAlgorithm::Wyhash),
     ];
 
     let mut group = c.benchmark_group("hash_comparison");
@@ -39,7 +31,7 @@ pub fn bench_hash_algorithms(c: &mut Criterion) {
         let test_data = vec![0xAA; size]; // Create test data filled with 0xAA
         
         for (algo_name, algo) in &algorithms {
-            let config = create_single_algo_config(algo.clone());
+            let config = ShardConfig::single(algo.clone());
             let shard = FastShard::with_config(1024, config);
             
             group.bench_with_input(
@@ -55,5 +47,28 @@ pub fn bench_hash_algorithms(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, bench_hash_algorithms);
+// Per-call cost on 8-byte keys: the bare `fnv` crate hasher against a full
+// `shard` call on an FNV-1a config. rustc inlines `FnvHasher` completely, so
+// the inlined kernel hashes at the same speed; the gap between the two is
+// tier selection and dispatch inside `shard`.
+pub fn bench_fnv_overhead(c: &mut Criterion) {
+    use std::hash::Hasher;
+
+    let key = [0xAAu8; 8];
+    let shard = FastShard::with_config(1024, ShardConfig::single(ShardAlgorithm::Fnv1a));
+    let mut group = c.benchmark_group("fnv_overhead");
+
+    group.bench_function("fnv_crate_hasher", |b| {
+        b.iter(|| {
+            let mut hasher = fnv::FnvHasher::default();
+            hasher.write(black_box(&key));
+            (hasher.finish() % 1024) as u32
+        })
+    });
+    group.bench_function("fast_shard_inline", |b| b.iter(|| shard.shard(black_box(&key))));
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_hash_algorithms, bench_fnv_overhead);
 criterion_main!(benches);
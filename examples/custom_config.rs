@@ -1,39 +1,15 @@
-use fast_shard::{FastShard, ShardConfig, ShardTier, ShardAlgorithm};
+use fast_shard::{FastShard, ShardAlgorithm, ShardConfig};
 
 fn main() {
-    // Define custom configuration
-    let config = ShardConfig {
-        tiers: vec![
-            ShardTier {
-                size_range: 0..=128,
-                algorithms: vec![
-                    ShardAlgorithm::Avx512,
-                    ShardAlgorithm::AesNi,
-                    ShardAlgorithm::Fnv1a,
-                ],
-            },
-            ShardTier {
-                size_range: 129..=1024,
-                algorithms: vec![
-                    ShardAlgorithm::Avx512,
-                    ShardAlgorithm::Avx2,
-                    ShardAlgorithm::Xxh3,
-                ],
-            },
-            ShardTier {
-                size_range: 1025..=4096,
-                algorithms: vec![
-                    ShardAlgorithm::Avx512,
-                    ShardAlgorithm::AesNi,
-                    ShardAlgorithm::Xxh3,
-                ],
-            },
-        ],
-        default_algorithms: vec![
-            ShardAlgorithm::Xxh3,
-            ShardAlgorithm::Fnv1a,
-        ],
-    };
+    // Tiers may be listed in any order; `build` sorts them and rejects gaps
+    // and overlaps.
+    let config = ShardConfig::builder()
+        .tier_up_to(128, vec![ShardAlgorithm::Avx512, ShardAlgorithm::AesNi, ShardAlgorithm::Fnv1a])
+        .tier(129..=1024, vec![ShardAlgorithm::Avx512, ShardAlgorithm::Avx2, ShardAlgorithm::Xxh3])
+        .tier_from(1025, vec![ShardAlgorithm::Avx512, ShardAlgorithm::AesNi, ShardAlgorithm::Xxh3])
+        .default_algorithms(vec![ShardAlgorithm::Xxh3, ShardAlgorithm::Fnv1a])
+        .build()
+        .expect("tiers must cover every key size once, with enabled algorithms");
 
     let shard = FastShard::with_config(1024, config);
     
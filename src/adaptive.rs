@@ -0,0 +1,195 @@
+// File: src/adaptive.rs
+//
+// Adaptive kernel selection. Each SIMD kernel has a portable scalar model
+// (`reference.rs`) that computes the same raw hash bit for bit, so choosing
+// between them per key-length band changes only speed, never placement.
+// Algorithms without such a pair (XXH3, FNV-1a, or a SIMD kernel that is not
+// usable on this build and host) always take their usual path. So does
+// SIMD128: calibration times kernels with `Instant`, which wasm32 lacks.
+
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Instant;
+
+use crate::cpu::CpuFeatures;
+use crate::{FastShard, ShardAlgorithm};
+
+// One band per key-length bit width: 0, 1, 2..=3, 4..=7, ...
+const BANDS: usize = usize::BITS as usize + 1;
+// Every this many adaptive hashes, the current key's band is re-timed.
+const SAMPLE_EVERY: u64 = 4096;
+const CALIBRATION_ROUNDS: u32 = 16;
+
+// Takes the key, the config's seed and the length to fold in.
+type Kernel = fn(&[u8], u64, usize) -> u64;
+
+pub(crate) struct AdaptiveKernels {
+    portable: [AtomicBool; BANDS],
+    calls: AtomicU64,
+}
+
+fn band(len: usize) -> usize {
+    (usize::BITS - len.leading_zeros()) as usize
+}
+
+impl AdaptiveKernels {
+    fn new() -> Self {
+        Self {
+            portable: core::array::from_fn(|_| AtomicBool::new(false)),
+            calls: AtomicU64::new(0),
+        }
+    }
+
+    // `Some` when `algorithm` has both a native and a portable kernel here.
+    pub(crate) fn hash(
+        &self,
+        key: &[u8],
+        seed: u64,
+        folded_len: usize,
+        algorithm: &ShardAlgorithm,
+        cpu: CpuFeatures,
+    ) -> Option<u64> {
+        let (native, portable) = kernel_pair(algorithm, cpu)?;
+        let band = band(key.len());
+        // A racy count is fine for sampling and avoids a locked increment.
+        let calls = self.calls.load(Ordering::Relaxed);
+        self.calls.store(calls.wrapping_add(1), Ordering::Relaxed);
+        if calls.is_multiple_of(SAMPLE_EVERY) {
+            self.calibrate(band, key, seed, folded_len, native, portable);
+        }
+        Some(if self.portable[band].load(Ordering::Relaxed) {
+            portable(key, seed, folded_len)
+        } else {
+            native(key, seed, folded_len)
+        })
+    }
+
+    fn calibrate(&self, band: usize, key: &[u8], seed: u64, folded_len: usize, native: Kernel, portable: Kernel) {
+        let time = |kernel: Kernel| {
+            let start = Instant::now();
+            for _ in 0..CALIBRATION_ROUNDS {
+                core::hint::black_box(kernel(core::hint::black_box(key), seed, folded_len));
+            }
+            start.elapsed()
+        };
+        let prefer_portable = time(portable) < time(native);
+        self.portable[band].store(prefer_portable, Ordering::Relaxed);
+    }
+
+    #[cfg(test)]
+    fn force(&self, portable: bool) {
+        for band in &self.portable {
+            band.store(portable, Ordering::Relaxed);
+        }
+        // Push the next calibration far away.
+        self.calls.store(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(all(feature = "avx512", target_arch = "x86_64"))]
+fn avx512_native(key: &[u8], seed: u64, folded_len: usize) -> u64 {
+    // Only handed out by `kernel_pair` when `CpuFeatures` reports the feature.
+    unsafe { crate::simd::avx512_hash_in(key, seed, folded_len, &mut [0u8; 64]) as u64 }
+}
+
+#[cfg(all(feature = "avx2", target_arch = "x86_64"))]
+fn avx2_native(key: &[u8], seed: u64, folded_len: usize) -> u64 {
+    unsafe { crate::simd::avx2_hash_in(key, seed, folded_len, &mut [0u8; 64]) as u64 }
+}
+
+#[cfg(all(feature = "aesni", target_arch = "x86_64"))]
+fn aesni_native(key: &[u8], seed: u64, folded_len: usize) -> u64 {
+    unsafe { crate::simd::aesni_hash_in(key, seed, folded_len, &mut [0u8; 64]) as u64 }
+}
+
+#[cfg(all(feature = "neon", target_arch = "aarch64"))]
+fn neon_native(key: &[u8], seed: u64, folded_len: usize) -> u64 {
+    unsafe { crate::neon::neon_hash_in(key, seed, folded_len, &mut [0u8; 64]) as u64 }
+}
+
+// Native and portable kernels for `algorithm`, when the native one is the
+// path `FastShard` would otherwise take on this build and host.
+#[cfg_attr(
+    not(any(
+        all(target_arch = "x86_64", any(feature = "avx512", feature = "avx2", feature = "aesni")),
+        all(target_arch = "aarch64", feature = "neon")
+    )),
+    allow(unused_variables)
+)]
+fn kernel_pair(algorithm: &ShardAlgorithm, cpu: CpuFeatures) -> Option<(Kernel, Kernel)> {
+    match algorithm {
+        #[cfg(all(feature = "avx512", target_arch = "x86_64"))]
+        ShardAlgorithm::Avx512 if cpu.avx512f => Some((avx512_native, |key, seed, len| {
+            crate::reference::avx512_hash_with_len(key, seed, len) as u64
+        })),
+        #[cfg(all(feature = "avx2", target_arch = "x86_64"))]
+        ShardAlgorithm::Avx2 if cpu.avx2 => Some((avx2_native, |key, seed, len| {
+            crate::reference::avx2_hash_with_len(key, seed, len) as u64
+        })),
+        #[cfg(all(feature = "neon", target_arch = "aarch64"))]
+        ShardAlgorithm::Neon if cpu.neon => Some((neon_native, |key, seed, len| {
+            crate::reference::aesni_hash_with_len(key, seed, len) as u64
+        })),
+        #[cfg(all(feature = "aesni", target_arch = "x86_64"))]
+        ShardAlgorithm::AesNi if cpu.aes => Some((aesni_native, |key, seed, len| {
+            crate::reference::aesni_hash_with_len(key, seed, len) as u64
+        })),
+        _ => None,
+    }
+}
+
+impl FastShard {
+    /// Lets each SIMD algorithm switch, per key-length band, between its
+    /// vector kernel and its bit-identical portable model, re-timing both on
+    /// a sampled key every few thousand hashes and keeping the faster.
+    /// Placement never changes; only the code path computing it does.
+    ///
+    /// The bookkeeping costs a few percent per hash, so this only pays off
+    /// where a portable model actually outruns its kernel on the dominant
+    /// sizes; compare `dominant_size_*` in `shard_benchmark` on the target
+    /// host.
+    pub fn with_adaptive_kernels(mut self) -> Self {
+        self.adaptive = Some(Box::new(AdaptiveKernels::new()));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ShardConfig;
+
+    #[test]
+    fn test_kernel_choice_never_changes_placement() {
+        let keys: Vec<Vec<u8>> = (0..300usize)
+            .map(|len| (0..len).map(|i| (i as u8).wrapping_mul(29) ^ len as u8).collect())
+            .collect();
+        let algorithms = [ShardAlgorithm::Avx512, ShardAlgorithm::Avx2, ShardAlgorithm::AesNi];
+        for (algo, seed) in algorithms.into_iter().flat_map(|algo| [(algo.clone(), 0), (algo, 264)]) {
+            let config = ShardConfig {
+                seed,
+                ..ShardConfig::single(algo.clone())
+            };
+            let fixed = FastShard::with_config(1021, config.clone());
+            let adaptive = FastShard::with_config(1021, config).with_adaptive_kernels();
+            let kernels = adaptive.adaptive.as_ref().unwrap();
+
+            // Calibrating on the first call, then each forced path.
+            assert_eq!(adaptive.shard(&keys[40]), fixed.shard(&keys[40]));
+            for portable in [true, false] {
+                kernels.force(portable);
+                for key in &keys {
+                    assert_eq!(adaptive.shard(key), fixed.shard(key), "{:?} len {}", algo, key.len());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_bands() {
+        assert_eq!(band(0), 0);
+        assert_eq!(band(1), 1);
+        assert_eq!(band(3), 2);
+        assert_eq!(band(4), 3);
+        assert_eq!(band(usize::MAX), BANDS - 1);
+    }
+}
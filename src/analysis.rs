@@ -0,0 +1,242 @@
+// File: src/analysis.rs
+use core::ops::RangeInclusive;
+
+use crate::{FastShard, ShardAlgorithm, ShardConfig};
+
+/// How one tier of a config serves a sample of key sizes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TierTuning {
+    pub size_range: RangeInclusive<usize>,
+    /// Fraction of the sample routed to this tier under the config's
+    /// `tier_selection`.
+    pub captured: f64,
+    /// The algorithm the tier resolves to on this build and host.
+    pub algorithm: ShardAlgorithm,
+}
+
+/// How evenly a set of keys spreads over the shards.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DistributionStats {
+    /// Keys per shard, indexed by shard id.
+    pub counts: Vec<u64>,
+    pub min: u64,
+    pub max: u64,
+    pub mean: f64,
+    /// Population standard deviation of `counts`.
+    pub stddev: f64,
+    /// Pearson's chi-square statistic against a uniform spread; near
+    /// `counts.len() - 1` for a good hash.
+    pub chi_square: f64,
+}
+
+impl DistributionStats {
+    pub(crate) fn from_counts(counts: Vec<u64>) -> Self {
+        let n = counts.len().max(1) as f64;
+        let mean = counts.iter().sum::<u64>() as f64 / n;
+        let squares: f64 = counts.iter().map(|&c| (c as f64 - mean).powi(2)).sum();
+        Self {
+            min: counts.iter().copied().min().unwrap_or(0),
+            max: counts.iter().copied().max().unwrap_or(0),
+            mean,
+            stddev: (squares / n).sqrt(),
+            chi_square: if mean > 0.0 { squares / mean } else { 0.0 },
+            counts,
+        }
+    }
+}
+
+impl FastShard {
+    /// Reports, per tier, the fraction of `sizes` it captures and the
+    /// algorithm it resolves to. Sizes no tier covers fall through to
+    /// `default_algorithms`, so the fractions then sum to less than 1.
+    pub fn tuning_report(&self, sizes: &[usize]) -> Vec<TierTuning> {
+        let mut counts = vec![0usize; self.config.tiers.len()];
+        for &size in sizes {
+            if let Some(idx) = self.tier_index(size) {
+                counts[idx] += 1;
+            }
+        }
+
+        self.config
+            .tiers
+            .iter()
+            .zip(counts)
+            .map(|(tier, count)| TierTuning {
+                size_range: tier.size_range.clone(),
+                captured: if sizes.is_empty() {
+                    0.0
+                } else {
+                    count as f64 / sizes.len() as f64
+                },
+                algorithm: self.get_available_algorithm(&tier.algorithms),
+            })
+            .collect()
+    }
+
+    /// How this instance spreads `keys`, e.g. a sample of production keys
+    /// checked against a candidate config before rollout. A `chi_square`
+    /// far above `shard_count - 1` flags a tier whose algorithm handles
+    /// these keys poorly. Does not record metrics.
+    pub fn distribution_stats(&self, keys: &[&[u8]]) -> DistributionStats {
+        let mut counts = vec![0u64; self.shard_count as usize];
+        for key in keys {
+            counts[self.reduce(self.hash64(key)) as usize] += 1;
+        }
+        DistributionStats::from_counts(counts)
+    }
+
+    /// Spreads `keys` over `shard_count` shards with algorithm `a` and with
+    /// algorithm `b` (each alone for every key size) and returns both
+    /// distributions, in that order. An algorithm that is unavailable on
+    /// this build or host resolves to its usual fallback.
+    pub fn compare_distributions(
+        keys: &[&[u8]],
+        a: ShardAlgorithm,
+        b: ShardAlgorithm,
+        shard_count: u32,
+    ) -> (DistributionStats, DistributionStats) {
+        let stats = |algorithm: ShardAlgorithm| {
+            let config = ShardConfig::single(algorithm);
+            let shard = FastShard::with_config(shard_count, config);
+            let mut counts = vec![0u64; shard_count as usize];
+            for key in keys {
+                counts[shard.shard(key) as usize] += 1;
+            }
+            DistributionStats::from_counts(counts)
+        };
+        (stats(a), stats(b))
+    }
+
+    /// Expected number of shards left empty when `num_keys` uniformly hashed
+    /// keys land on `shard_count` shards (balls into bins):
+    /// `shard_count * (1 - 1/shard_count)^num_keys`.
+    pub fn expected_empty_shards(num_keys: u64, shard_count: u32) -> f64 {
+        if shard_count == 0 || num_keys == 0 {
+            return shard_count as f64;
+        }
+        let n = shard_count as f64;
+        // `ln_1p` keeps precision when `1/shard_count` is tiny.
+        n * (num_keys as f64 * (-1.0 / n).ln_1p()).exp()
+    }
+
+    /// Number of shards that none of `keys` map to, the measured counterpart
+    /// of [`expected_empty_shards`](FastShard::expected_empty_shards).
+    pub fn empty_shards<K: AsRef<[u8]>>(&self, keys: impl IntoIterator<Item = K>) -> u32 {
+        if self.shard_count == 1 {
+            // One shard takes every key; no need to hash them.
+            return keys.into_iter().next().is_none() as u32;
+        }
+        let mut hit = vec![false; self.shard_count as usize];
+        for key in keys {
+            hit[self.reduce(self.hash64(key.as_ref())) as usize] = true;
+        }
+        hit.iter().filter(|&&hit| !hit).count() as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fallback_algorithm, ShardTier};
+
+    #[test]
+    fn test_tuning_report() {
+        let config = ShardConfig {
+            tiers: vec![
+                ShardTier {
+                    size_range: 0..=16,
+                    algorithms: vec![ShardAlgorithm::Xxh3],
+                },
+                ShardTier {
+                    size_range: 17..=1024,
+                    algorithms: vec![ShardAlgorithm::Xxh3],
+                },
+            ],
+            default_algorithms: vec![ShardAlgorithm::Xxh3],
+            ..ShardConfig::default()
+        };
+        let shard = FastShard::with_config(16, config);
+
+        let report = shard.tuning_report(&[4, 8, 16, 100, 2000, 4096]);
+        assert_eq!(report.len(), 2);
+        assert_eq!(report[0].size_range, 0..=16);
+        assert_eq!(report[0].captured, 0.5);
+        assert_eq!(report[1].size_range, 17..=1024);
+        assert!((report[1].captured - 1.0 / 6.0).abs() < 1e-12);
+        for tier in &report {
+            assert_eq!(tier.algorithm, shard.get_available_algorithm(&[ShardAlgorithm::Xxh3]));
+        }
+
+        assert!(shard.tuning_report(&[]).iter().all(|tier| tier.captured == 0.0));
+    }
+
+    #[test]
+    fn test_expected_empty_shards_matches_measurement() {
+        assert_eq!(FastShard::expected_empty_shards(0, 64), 64.0);
+        assert_eq!(FastShard::expected_empty_shards(5, 1), 0.0);
+
+        // ~357 of 4096 shards expected empty.
+        let expected = FastShard::expected_empty_shards(10_000, 4096);
+        assert!((expected - 356.6).abs() < 0.5, "{}", expected);
+
+        // The model assumes independent placements: use random-looking keys
+        // (sequential ones spread more evenly than chance under FNV-1a) and a
+        // scalar hash (the default config may pick a weak SIMD kernel).
+        let config = ShardConfig::single(fallback_algorithm());
+        let shard = FastShard::with_config(4096, config);
+        let measured = shard.empty_shards((0..10_000u64).map(|i| crate::mix::splitmix64(i).to_le_bytes()));
+        assert!((measured as f64 - expected).abs() < expected * 0.15, "{} vs {}", measured, expected);
+    }
+
+    #[test]
+    #[cfg(feature = "xxh3")]
+    fn test_distribution_stats_on_random_keys() {
+        use rand::{Rng, SeedableRng};
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(276);
+        let owned: Vec<[u8; 16]> = (0..100_000).map(|_| rng.gen()).collect();
+        let keys: Vec<&[u8]> = owned.iter().map(|k| &k[..]).collect();
+        let shard = FastShard::with_config(256, ShardConfig::deterministic()).with_metrics();
+
+        let stats = shard.distribution_stats(&keys);
+        assert_eq!(stats.counts.len(), 256);
+        assert_eq!(stats.counts.iter().sum::<u64>(), 100_000);
+        assert!(stats.min as f64 <= stats.mean && stats.mean <= stats.max as f64);
+        assert!(stats.counts[shard.shard(keys[0]) as usize] > 0);
+        // 255 degrees of freedom: two-sided p = 0.002 bounds are ~186 and ~330.
+        assert!(stats.chi_square > 186.0 && stats.chi_square < 330.0, "{:?}", stats.chi_square);
+        // Only the `shard` call above was recorded.
+        assert_eq!(shard.shard_counts().iter().sum::<u64>(), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "xxh3")]
+    fn test_compare_distributions_on_permuted_keys() {
+        // Every key is the same eight 4-byte words in a different order,
+        // which a plain lane sum could not tell apart.
+        let words: Vec<[u8; 4]> = (0..8u32).map(|i| (i * 0x0101_0101 + 7).to_le_bytes()).collect();
+        let owned: Vec<Vec<u8>> = (0..2000usize)
+            .map(|seed| {
+                let mut order: Vec<usize> = (0..8).collect();
+                let mut x = seed as u64 + 1;
+                for i in (1..8).rev() {
+                    x = crate::mix::splitmix64(x);
+                    order.swap(i, (x % (i as u64 + 1)) as usize);
+                }
+                order.iter().flat_map(|&w| words[w]).collect()
+            })
+            .collect();
+        let keys: Vec<&[u8]> = owned.iter().map(|k| k.as_slice()).collect();
+
+        let (xxh3, avx512) =
+            FastShard::compare_distributions(&keys, ShardAlgorithm::Xxh3, ShardAlgorithm::Avx512, 64);
+        for stats in [&xxh3, &avx512] {
+            assert_eq!(stats.counts.iter().sum::<u64>(), 2000);
+            assert!((stats.mean - 2000.0 / 64.0).abs() < 1e-9);
+            // The shuffles are nearly all distinct keys, so neither algorithm
+            // should pile them up.
+            assert!(stats.stddev < stats.mean / 2.0, "{:?}", stats);
+            assert!(stats.max < 2 * stats.mean as u64 + 20, "{:?}", stats);
+        }
+    }
+}
@@ -0,0 +1,104 @@
+// File: src/auto.rs
+//
+// Startup selection: time every algorithm the default config would consider
+// on one representative key per size bucket, then build a config whose tiers
+// name only the winners. The timing happens once, in `new_auto`; afterwards
+// the instance is an ordinary `FastShard` over a fixed config.
+
+use core::ops::RangeInclusive;
+use std::time::{Duration, Instant};
+
+use crate::{FastShard, ShardAlgorithm, ShardConfig, ShardTier};
+
+// Each bucket and the key length timed for it.
+const BUCKETS: [(RangeInclusive<usize>, usize); 3] = [(0..=32, 8), (33..=256, 64), (257..=usize::MAX, 1024)];
+const ROUNDS: u32 = 64;
+// The best of several trials, to shrug off a preemption mid-trial.
+const TRIALS: u32 = 5;
+
+impl FastShard {
+    /// A `FastShard` whose tiers are picked by timing each algorithm the
+    /// default config lists and this host runs on 8-, 64- and 1024-byte keys
+    /// and keeping the fastest for keys up to 32 bytes, up to 256 bytes and
+    /// beyond. Costs a few milliseconds once; after that every key hashes
+    /// along a fixed path, so its shard never changes for this instance.
+    ///
+    /// The choice follows this host's timings, so two processes may pick
+    /// different algorithms and disagree on placement. Where producers must
+    /// agree, use [`ShardConfig::deterministic`].
+    ///
+    /// # Panics
+    ///
+    /// Like [`FastShard::new`], on a zero `shard_count`.
+    pub fn new_auto(shard_count: u32) -> Self {
+        let probe = FastShard::new(shard_count.max(1));
+        let defaults = ShardConfig::default();
+        let mut candidates: Vec<ShardAlgorithm> = Vec::new();
+        for algo in defaults.tiers.iter().flat_map(|tier| &tier.algorithms).chain(&defaults.default_algorithms) {
+            // Skip kernels that would only run their fallback here.
+            let runs = probe.get_available_algorithm(core::slice::from_ref(algo)) == *algo;
+            if runs && !candidates.contains(algo) {
+                candidates.push(algo.clone());
+            }
+        }
+
+        let tiers: Vec<ShardTier> = BUCKETS
+            .iter()
+            .map(|(size_range, len)| {
+                let key: Vec<u8> = (0..*len).map(|i| (i as u8).wrapping_mul(31)).collect();
+                // Ties go to the earlier, i.e. the default-preferred, entry.
+                let fastest = candidates
+                    .iter()
+                    .min_by_key(|&algo| time(&probe, &key, algo))
+                    .cloned()
+                    .unwrap_or_else(crate::fallback_algorithm);
+                ShardTier {
+                    size_range: size_range.clone(),
+                    algorithms: vec![fastest],
+                }
+            })
+            .collect();
+        let config = ShardConfig {
+            default_algorithms: tiers[0].algorithms.clone(),
+            tiers,
+            ..defaults
+        };
+        Self::with_config(shard_count, config)
+    }
+}
+
+fn time(shard: &FastShard, key: &[u8], algo: &ShardAlgorithm) -> Duration {
+    (0..TRIALS)
+        .map(|_| {
+            let start = Instant::now();
+            for _ in 0..ROUNDS {
+                core::hint::black_box(shard.hash_with_algorithm(core::hint::black_box(key), algo.clone()));
+            }
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capabilities;
+
+    #[test]
+    fn test_new_auto_picks_concrete_algorithms() {
+        let shard = FastShard::new_auto(64);
+        assert_eq!(shard.shard_count(), 64);
+        let runnable = capabilities();
+        for len in [0, 8, 32, 33, 64, 256, 257, 1024, 1 << 20] {
+            let algo = shard.which_algorithm(len);
+            assert!(runnable.contains(&algo), "{:?} for {} bytes", algo, len);
+        }
+
+        // The choice is made once: placement is stable from then on.
+        let keys: Vec<Vec<u8>> = (0..2000usize).map(|i| vec![i as u8; i % 300]).collect();
+        let first: Vec<u32> = keys.iter().map(|key| shard.shard(key)).collect();
+        assert!(first.iter().all(|&s| s < 64));
+        assert_eq!(keys.iter().map(|key| shard.shard(key)).collect::<Vec<_>>(), first);
+    }
+}
@@ -0,0 +1,361 @@
+// File: src/batch.rs
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::{FastShard, ShardAlgorithm};
+
+/// Reusable tail buffer for [`FastShard::shard_batch_with_scratch`]. The SIMD
+/// kernels pad a key's partial last chunk here instead of in a freshly zeroed
+/// stack array, clearing only the bytes they copied in.
+#[derive(Debug, Clone)]
+pub struct ShardScratch {
+    // All zero between uses.
+    #[cfg_attr(
+        not(any(
+            all(target_arch = "x86_64", any(feature = "avx512", feature = "avx2", feature = "aesni")),
+            all(target_arch = "aarch64", feature = "neon"),
+            all(target_arch = "wasm32", target_feature = "simd128", feature = "simd128")
+        )),
+        allow(dead_code)
+    )]
+    tail: [u8; 64],
+}
+
+impl ShardScratch {
+    /// An all-zero scratch buffer.
+    pub fn new() -> Self {
+        Self { tail: [0; 64] }
+    }
+}
+
+impl Default for ShardScratch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FastShard {
+    /// Fills `out[i]` with the shard of `keys[i]`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `keys` and `out` differ in length.
+    pub fn shard_batch(&self, keys: &[&[u8]], out: &mut [u32]) {
+        assert_eq!(keys.len(), out.len(), "keys and out must have the same length");
+        for (key, slot) in keys.iter().zip(out.iter_mut()) {
+            *slot = self.shard(key);
+        }
+    }
+
+    /// [`FastShard::shard_batch`] into a freshly allocated vector.
+    pub fn shard_batch_vec(&self, keys: &[&[u8]]) -> Vec<u32> {
+        let mut out = vec![0; keys.len()];
+        self.shard_batch(keys, &mut out);
+        out
+    }
+
+    /// [`FastShard::shard_batch_vec`] spread over rayon's global thread pool,
+    /// for batches large enough to repay the fan-out (hundreds of thousands
+    /// of keys). Placement is identical; metrics, if enabled, are recorded
+    /// from every thread. Requires the `rayon` feature.
+    #[cfg(feature = "rayon")]
+    pub fn par_shard_batch(&self, keys: &[&[u8]]) -> Vec<u32> {
+        use rayon::prelude::*;
+        keys.par_iter().map(|key| self.shard(key)).collect()
+    }
+
+    /// Groups `keys` by shard: the result has `shard_count` buckets, and
+    /// bucket `i` holds the keys that [`FastShard::shard`] places on shard
+    /// `i`, in input order. Handy for fanning a batch out to per-shard
+    /// writers; metrics, if enabled, are recorded once per key.
+    pub fn partition<'a>(&self, keys: &[&'a [u8]]) -> Vec<Vec<&'a [u8]>> {
+        let mut buckets = vec![Vec::new(); self.shard_count as usize];
+        for &key in keys {
+            buckets[self.shard(key) as usize].push(key);
+        }
+        buckets
+    }
+
+    /// Like [`FastShard::shard_batch`], but hashes all keys of one size tier
+    /// before moving on to the next, so each tier's algorithm is resolved
+    /// once and its kernel stays hot. `out[i]` still holds the shard of
+    /// `keys[i]`. On the default two-tier config this benchmarks at parity
+    /// with `shard_batch` (see `batch_mixed_*` in `shard_benchmark`), so it
+    /// mainly helps configs whose tiers use very different kernels.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `keys` and `out` differ in length.
+    pub fn shard_batch_by_tier(&self, keys: &[&[u8]], out: &mut [u32]) {
+        assert_eq!(keys.len(), out.len(), "keys and out must have the same length");
+        if self.has_key_hooks() {
+            self.shard_batch(keys, out);
+            return;
+        }
+
+        // One bucket per tier plus a trailing one for the defaults.
+        let defaults = self.config.tiers.len();
+        let mut buckets: Vec<Vec<usize>> = vec![Vec::new(); defaults + 1];
+        for (i, key) in keys.iter().enumerate() {
+            buckets[self.tier_index(key.len()).unwrap_or(defaults)].push(i);
+        }
+
+        for (tier, bucket) in buckets.iter().enumerate() {
+            if bucket.is_empty() {
+                continue;
+            }
+            let algorithm = self.get_algorithm_for_tier((tier < defaults).then_some(tier));
+            for &i in bucket {
+                out[i] = self.reduce(self.hash_with_algorithm(keys[i], algorithm.clone()));
+                self.record(keys[i].len(), Some(&algorithm), out[i]);
+            }
+        }
+    }
+
+    /// Like [`FastShard::shard_batch`], but the SIMD kernels pad each key's
+    /// tail in `scratch`, reused across keys, rather than zeroing a fresh
+    /// buffer per key. Results match `shard`. Instances with metrics, an
+    /// observer, adaptive kernels or key hooks take the `shard_batch` path.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `keys` and `out` differ in length.
+    pub fn shard_batch_with_scratch(&self, keys: &[&[u8]], out: &mut [u32], scratch: &mut ShardScratch) {
+        assert_eq!(keys.len(), out.len(), "keys and out must have the same length");
+        if self.has_key_hooks() || self.metrics.is_some() || self.observer.is_some() || self.has_adaptive_kernels() {
+            self.shard_batch(keys, out);
+            return;
+        }
+        for (key, slot) in keys.iter().zip(out.iter_mut()) {
+            let algorithm = self.get_algorithm_for_size(key.len());
+            *slot = self.reduce(self.hash_with_scratch(key, algorithm, scratch));
+        }
+    }
+
+    // `hash_with_algorithm`, padding SIMD tails in `scratch`.
+    #[cfg_attr(
+        not(any(
+            all(target_arch = "x86_64", any(feature = "avx512", feature = "avx2", feature = "aesni")),
+            all(target_arch = "aarch64", feature = "neon"),
+            all(target_arch = "wasm32", target_feature = "simd128", feature = "simd128")
+        )),
+        allow(unused_variables)
+    )]
+    fn hash_with_scratch(&self, key: &[u8], algorithm: ShardAlgorithm, scratch: &mut ShardScratch) -> u64 {
+        let (seed, len, tail) = (self.config.seed, self.folded_len(key), &mut scratch.tail);
+        // Only called with the feature confirmed by `self.cpu`; the raw
+        // kernel hashes are finalized as in `hash_with_algorithm`.
+        match algorithm {
+            #[cfg(all(feature = "avx512", target_arch = "x86_64"))]
+            ShardAlgorithm::Avx512 if self.cpu.avx512f => unsafe {
+                crate::mix::finalize(crate::simd::avx512_hash_in(key, seed, len, tail) as u64)
+            },
+            #[cfg(all(feature = "avx2", target_arch = "x86_64"))]
+            ShardAlgorithm::Avx2 if self.cpu.avx2 => unsafe {
+                crate::mix::finalize(crate::simd::avx2_hash_in(key, seed, len, tail) as u64)
+            },
+            #[cfg(all(feature = "aesni", target_arch = "x86_64"))]
+            ShardAlgorithm::AesNi if self.cpu.aes => unsafe {
+                crate::mix::finalize(crate::simd::aesni_hash_in(key, seed, len, tail) as u64)
+            },
+            #[cfg(all(feature = "neon", target_arch = "aarch64"))]
+            ShardAlgorithm::Neon if self.cpu.neon => unsafe {
+                crate::mix::finalize(crate::neon::neon_hash_in(key, seed, len, tail) as u64)
+            },
+            #[cfg(all(target_arch = "wasm32", target_feature = "simd128", feature = "simd128"))]
+            ShardAlgorithm::Simd128 => crate::mix::finalize(crate::wasm::simd128_hash_in(key, seed, len, tail) as u64),
+            _ => self.hash_with_algorithm(key, algorithm),
+        }
+    }
+
+    /// Shards fixed-size records packed back to back in `data`, keyed on the
+    /// `key_range` bytes of each record; `out[i]` receives record `i`'s
+    /// shard. Every key has the same length, so the algorithm is resolved
+    /// once and its kernel runs directly over each record's key bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `record_len` is zero, `data` is not a whole number of
+    /// records, `key_range` falls outside a record, or `out` does not hold
+    /// one slot per record.
+    pub fn shard_records(&self, data: &[u8], record_len: usize, key_range: Range<usize>, out: &mut [u32]) {
+        assert!(record_len > 0, "record_len must be nonzero");
+        assert_eq!(data.len() % record_len, 0, "data must hold whole records");
+        assert!(
+            key_range.start <= key_range.end && key_range.end <= record_len,
+            "key_range must lie within a record"
+        );
+        assert_eq!(out.len(), data.len() / record_len, "out must hold one slot per record");
+
+        if self.has_key_hooks() {
+            for (record, slot) in data.chunks_exact(record_len).zip(out.iter_mut()) {
+                *slot = self.shard(&record[key_range.clone()]);
+            }
+            return;
+        }
+
+        let algorithm = self.get_algorithm_for_size(key_range.len());
+        for (record, slot) in data.chunks_exact(record_len).zip(out.iter_mut()) {
+            *slot = self.reduce(self.hash_with_algorithm(&record[key_range.clone()], algorithm.clone()));
+            self.record(key_range.len(), Some(&algorithm), *slot);
+        }
+    }
+}
+
+#[cfg(feature = "bitset")]
+impl FastShard {
+    /// The set of shards `keys` map to, as a bitset with one bit per shard:
+    /// bit `i` is set iff some key lands on shard `i`. One bit per shard
+    /// keeps fan-out planning cheap even for very large shard counts.
+    pub fn touched_shards_bitset(&self, keys: &[&[u8]]) -> fixedbitset::FixedBitSet {
+        let mut touched = fixedbitset::FixedBitSet::with_capacity(self.shard_count as usize);
+        for key in keys {
+            touched.insert(self.shard(key) as usize);
+        }
+        touched
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shard_batch_by_tier_preserves_order() {
+        let shard = FastShard::new(64);
+        let owned: Vec<Vec<u8>> = (0..500u32)
+            .map(|i| vec![i as u8; [4, 40, 9, 300, 16, 17][i as usize % 6]])
+            .collect();
+        let keys: Vec<&[u8]> = owned.iter().map(|k| k.as_slice()).collect();
+
+        let mut plain = vec![0u32; keys.len()];
+        let mut grouped = vec![0u32; keys.len()];
+        shard.shard_batch(&keys, &mut plain);
+        shard.shard_batch_by_tier(&keys, &mut grouped);
+
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(grouped[i], shard.shard(key));
+        }
+        assert_eq!(grouped, plain);
+    }
+
+    #[test]
+    fn test_shard_batch_vec_matches_shard() {
+        let shard = FastShard::new(1000);
+        let owned: Vec<Vec<u8>> = (0..300usize).map(|i| (0..i).map(|j| (i ^ j) as u8).collect()).collect();
+        let keys: Vec<&[u8]> = owned.iter().map(|k| k.as_slice()).collect();
+
+        let expected: Vec<u32> = keys.iter().map(|key| shard.shard(key)).collect();
+        assert_eq!(shard.shard_batch_vec(&keys), expected);
+        assert!(shard.shard_batch_vec(&[]).is_empty());
+    }
+
+    // Shared across rayon's threads by reference.
+    #[test]
+    fn test_fast_shard_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<FastShard>();
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn test_par_shard_batch_matches_serial() {
+        let shard = FastShard::new(1000).with_metrics();
+        let owned: Vec<Vec<u8>> = (0..50_000u32).map(|i| i.to_le_bytes().repeat(8)).collect();
+        let keys: Vec<&[u8]> = owned.iter().map(|k| k.as_slice()).collect();
+
+        let serial = shard.shard_batch_vec(&keys);
+        assert_eq!(shard.par_shard_batch(&keys), serial);
+        assert!(shard.par_shard_batch(&[]).is_empty());
+        assert_eq!(shard.shard_counts().iter().sum::<u64>(), 100_000);
+    }
+
+    #[test]
+    fn test_shard_records_matches_per_record_shard() {
+        let shard = FastShard::new(256);
+        let record_len = 48;
+        let data: Vec<u8> = (0..record_len * 100).map(|i| (i * 7 % 251) as u8).collect();
+        let mut out = vec![0u32; 100];
+        shard.shard_records(&data, record_len, 8..40, &mut out);
+
+        for (i, record) in data.chunks(record_len).enumerate() {
+            assert_eq!(out[i], shard.shard(&record[8..40]));
+        }
+    }
+
+    #[test]
+    fn test_shard_batch_with_scratch_matches_shard() {
+        let owned: Vec<Vec<u8>> = (0..600u32)
+            .map(|i| (0..[0, 3, 8, 15, 16, 31, 33, 64, 70, 200][i as usize % 10]).map(|j| (i + j) as u8).collect())
+            .collect();
+        let keys: Vec<&[u8]> = owned.iter().map(|k| k.as_slice()).collect();
+        let algorithms = [
+            ShardAlgorithm::Avx512,
+            ShardAlgorithm::Avx2,
+            ShardAlgorithm::AesNi,
+            ShardAlgorithm::Neon,
+            ShardAlgorithm::Simd128,
+        ];
+        let mut shards: Vec<FastShard> = algorithms
+            .into_iter()
+            .map(|algorithm| {
+                let config = crate::ShardConfig::single(algorithm);
+                FastShard::with_config(1021, config)
+            })
+            .collect();
+        shards.push(FastShard::new(1021));
+        shards.push(FastShard::new(1021).with_metrics());
+
+        let mut scratch = ShardScratch::new();
+        for shard in &shards {
+            let mut out = vec![0u32; keys.len()];
+            shard.shard_batch_with_scratch(&keys, &mut out, &mut scratch);
+            for (key, &got) in keys.iter().zip(&out) {
+                assert_eq!(got, shard.shard(key), "len {}", key.len());
+            }
+            assert!(scratch.tail.iter().all(|&byte| byte == 0));
+        }
+    }
+
+    #[test]
+    fn test_partition_groups_keys_by_shard() {
+        let shard = FastShard::new(37);
+        let owned: Vec<Vec<u8>> = (0..2_000u32).map(|i| format!("user:{}", i % 1_500).into_bytes()).collect();
+        let keys: Vec<&[u8]> = owned.iter().map(|k| k.as_slice()).collect();
+
+        let buckets = shard.partition(&keys);
+        assert_eq!(buckets.len(), 37);
+        for (i, bucket) in buckets.iter().enumerate() {
+            assert!(bucket.iter().all(|key| shard.shard(key) as usize == i));
+            // Input order survives within each bucket.
+            let expected: Vec<&[u8]> =
+                keys.iter().copied().filter(|key| shard.shard(key) as usize == i).collect();
+            assert_eq!(bucket, &expected);
+        }
+
+        let mut flattened: Vec<&[u8]> = buckets.into_iter().flatten().collect();
+        let mut sorted = keys.clone();
+        flattened.sort_unstable();
+        sorted.sort_unstable();
+        assert_eq!(flattened, sorted);
+
+        assert!(shard.partition(&[]).iter().all(|bucket| bucket.is_empty()));
+    }
+
+    #[test]
+    #[cfg(feature = "bitset")]
+    fn test_touched_shards_bitset_matches_shard() {
+        let shard = FastShard::new(100_000);
+        let owned: Vec<Vec<u8>> = (0..300u32).map(|i| format!("order-{}", i % 250).into_bytes()).collect();
+        let keys: Vec<&[u8]> = owned.iter().map(|k| k.as_slice()).collect();
+
+        let touched = shard.touched_shards_bitset(&keys);
+        assert_eq!(touched.len(), 100_000);
+        let mut expected: Vec<usize> = keys.iter().map(|k| shard.shard(k) as usize).collect();
+        expected.sort_unstable();
+        expected.dedup();
+        assert_eq!(touched.ones().collect::<Vec<_>>(), expected);
+        assert_eq!(shard.touched_shards_bitset(&[]).count_ones(..), 0);
+    }
+}
@@ -0,0 +1,180 @@
+// File: src/builder.rs
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+
+use crate::{fallback_algorithm, ConfigError, ShardAlgorithm, ShardConfig, ShardTier};
+
+/// Builds a [`ShardConfig`] whose tiers cover every key size exactly once.
+///
+/// Tiers may be added in any order; `build` sorts them by range start and
+/// rejects gaps and overlaps.
+#[derive(Debug, Clone, Default)]
+pub struct ShardConfigBuilder {
+    tiers: Vec<ShardTier>,
+    default_algorithms: Option<Vec<ShardAlgorithm>>,
+}
+
+impl ShardConfigBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn tier(mut self, size_range: RangeInclusive<usize>, algorithms: Vec<ShardAlgorithm>) -> Self {
+        self.tiers.push(ShardTier {
+            size_range,
+            algorithms,
+        });
+        self
+    }
+
+    /// Adds a tier covering `0..=max`.
+    pub fn tier_up_to(self, max: usize, algorithms: Vec<ShardAlgorithm>) -> Self {
+        self.tier(0..=max, algorithms)
+    }
+
+    /// Adds a tier covering `min..=usize::MAX`.
+    pub fn tier_from(self, min: usize, algorithms: Vec<ShardAlgorithm>) -> Self {
+        self.tier(min..=usize::MAX, algorithms)
+    }
+
+    /// Defaults to the build's final fallback algorithm when unset.
+    pub fn default_algorithms(mut self, algorithms: Vec<ShardAlgorithm>) -> Self {
+        self.default_algorithms = Some(algorithms);
+        self
+    }
+
+    pub fn build(mut self) -> Result<ShardConfig, ConfigError> {
+        self.tiers.sort_by_key(|tier| *tier.size_range.start());
+        check_coverage(&self.tiers)?;
+
+        let config = ShardConfig {
+            tiers: self.tiers,
+            default_algorithms: self
+                .default_algorithms
+                .unwrap_or_else(|| vec![fallback_algorithm()]),
+            ..ShardConfig::default()
+        };
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+impl ShardConfig {
+    pub fn builder() -> ShardConfigBuilder {
+        ShardConfigBuilder::new()
+    }
+}
+
+// Checks that tiers sorted by start cover `0..=usize::MAX` exactly once.
+fn check_coverage(tiers: &[ShardTier]) -> Result<(), ConfigError> {
+    let mut next = Some(0usize);
+    let mut previous: Option<&RangeInclusive<usize>> = None;
+    for tier in tiers {
+        let range = &tier.size_range;
+        if range.is_empty() {
+            return Err(ConfigError::EmptyRange(range.clone()));
+        }
+        match next {
+            Some(expected) if *range.start() > expected => {
+                return Err(ConfigError::Gap(expected..=*range.start() - 1));
+            }
+            Some(expected) if *range.start() == expected => {}
+            _ => {
+                return Err(ConfigError::Overlap {
+                    first: previous.cloned().unwrap_or(range.clone()),
+                    second: range.clone(),
+                });
+            }
+        }
+        next = range.end().checked_add(1);
+        previous = Some(range);
+    }
+    match next {
+        Some(start) => Err(ConfigError::Gap(start..=usize::MAX)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FastShard;
+
+    #[test]
+    fn test_half_open_tiers_cover_all_sizes() {
+        let config = ShardConfig::builder()
+            .tier_from(17, vec![fallback_algorithm()])
+            .tier_up_to(16, vec![fallback_algorithm()])
+            .build()
+            .unwrap();
+
+        assert_eq!(config.tiers[0].size_range, 0..=16);
+        assert_eq!(config.tiers[1].size_range, 17..=usize::MAX);
+        for size in [0, 1, 16, 17, 4096, usize::MAX] {
+            assert!(config.tiers.iter().any(|tier| tier.size_range.contains(&size)));
+        }
+
+        let shard = FastShard::with_config(64, config);
+        assert!(shard.shard(b"hello") < 64);
+
+        assert_eq!(
+            ShardConfig::builder()
+                .tier_up_to(16, vec![fallback_algorithm()])
+                .tier_from(32, vec![fallback_algorithm()])
+                .build()
+                .unwrap_err(),
+            ConfigError::Gap(17..=31)
+        );
+        assert_eq!(
+            ShardConfig::builder()
+                .tier_up_to(16, vec![fallback_algorithm()])
+                .build()
+                .unwrap_err(),
+            ConfigError::Gap(17..=usize::MAX)
+        );
+    }
+
+    #[test]
+    fn test_builds_sorted_multi_tier_config() {
+        let config = ShardConfig::builder()
+            .tier_from(1025, vec![ShardAlgorithm::Xxh3])
+            .tier_up_to(128, vec![ShardAlgorithm::Fnv1a])
+            .tier(129..=1024, vec![ShardAlgorithm::Avx2, ShardAlgorithm::Xxh3])
+            .default_algorithms(vec![ShardAlgorithm::Fnv1a])
+            .build();
+        if !(ShardAlgorithm::Xxh3.is_enabled() && ShardAlgorithm::Fnv1a.is_enabled()) {
+            assert!(matches!(config, Err(ConfigError::AlgorithmDisabled(_))));
+            return;
+        }
+        let config = config.unwrap();
+        let ranges: Vec<_> = config.tiers.iter().map(|tier| tier.size_range.clone()).collect();
+        assert_eq!(ranges, vec![0..=128, 129..=1024, 1025..=usize::MAX]);
+        assert_eq!(config.tiers[1].algorithms, vec![ShardAlgorithm::Avx2, ShardAlgorithm::Xxh3]);
+        assert_eq!(config.default_algorithms, vec![ShardAlgorithm::Fnv1a]);
+        assert_eq!(config.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_rejects_overlapping_tiers() {
+        let err = ShardConfig::builder()
+            .tier_up_to(128, vec![fallback_algorithm()])
+            .tier(100..=1024, vec![fallback_algorithm()])
+            .tier_from(1025, vec![fallback_algorithm()])
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ConfigError::Overlap {
+                first: 0..=128,
+                second: 100..=1024,
+            }
+        );
+
+        let duplicate = ShardConfig::builder()
+            .tier_from(0, vec![fallback_algorithm()])
+            .tier_from(0, vec![fallback_algorithm()])
+            .build();
+        assert!(matches!(duplicate, Err(ConfigError::Overlap { .. })));
+    }
+}
@@ -0,0 +1,125 @@
+// File: src/cache.rs
+//
+// Optional per-length memo of the algorithm `get_algorithm_for_size` picks,
+// for workloads that only ever see a handful of key lengths. Each slot packs
+// `(len << 8) | (algorithm code + 1)` into one atomic word, so lookups need no
+// lock and a torn read is impossible; zero marks an empty slot. Slots are
+// direct-mapped by length and the latest length to land in a slot wins.
+
+use alloc::boxed::Box;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{FastShard, ShardAlgorithm};
+
+const SLOTS: usize = 64;
+
+pub(crate) struct AlgorithmCache {
+    slots: [AtomicU64; SLOTS],
+}
+
+impl AlgorithmCache {
+    pub(crate) fn new() -> Self {
+        Self {
+            slots: core::array::from_fn(|_| AtomicU64::new(0)),
+        }
+    }
+
+    pub(crate) fn get(&self, len: usize) -> Option<ShardAlgorithm> {
+        let packed = self.slots[len % SLOTS].load(Ordering::Relaxed);
+        if packed == 0 || packed >> 8 != len as u64 {
+            return None;
+        }
+        decode(packed as u8 - 1)
+    }
+
+    pub(crate) fn insert(&self, len: usize, algorithm: &ShardAlgorithm) {
+        // Lengths too large to pack are simply never cached.
+        if (len as u64) >> 56 == 0 {
+            let packed = (len as u64) << 8 | (encode(algorithm) as u64 + 1);
+            self.slots[len % SLOTS].store(packed, Ordering::Relaxed);
+        }
+    }
+}
+
+fn encode(algorithm: &ShardAlgorithm) -> u8 {
+    match algorithm {
+        ShardAlgorithm::Avx512 => 0,
+        ShardAlgorithm::Avx2 => 1,
+        ShardAlgorithm::AesNi => 2,
+        ShardAlgorithm::Fnv1a => 3,
+        ShardAlgorithm::Xxh3 => 4,
+        ShardAlgorithm::StdDefault => 5,
+        ShardAlgorithm::Neon => 6,
+        ShardAlgorithm::Simd128 => 7,
+        ShardAlgorithm::Wyhash => 8,
+        ShardAlgorithm::Crc32c => 9,
+        ShardAlgorithm::SipHash13 => 10,
+    }
+}
+
+fn decode(code: u8) -> Option<ShardAlgorithm> {
+    match code {
+        0 => Some(ShardAlgorithm::Avx512),
+        1 => Some(ShardAlgorithm::Avx2),
+        2 => Some(ShardAlgorithm::AesNi),
+        3 => Some(ShardAlgorithm::Fnv1a),
+        4 => Some(ShardAlgorithm::Xxh3),
+        5 => Some(ShardAlgorithm::StdDefault),
+        6 => Some(ShardAlgorithm::Neon),
+        7 => Some(ShardAlgorithm::Simd128),
+        8 => Some(ShardAlgorithm::Wyhash),
+        9 => Some(ShardAlgorithm::Crc32c),
+        10 => Some(ShardAlgorithm::SipHash13),
+        _ => None,
+    }
+}
+
+impl FastShard {
+    /// Remembers the algorithm chosen for each recently seen key length so
+    /// repeated lengths skip the tier scan. Worth it only when keys come in
+    /// a few fixed lengths and the config has many tiers; placement is
+    /// unchanged.
+    pub fn with_algorithm_cache(mut self) -> Self {
+        self.algorithm_cache = Some(Box::new(AlgorithmCache::new()));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fallback_algorithm, ShardConfig, ShardTier};
+
+    #[test]
+    fn test_cached_selection_matches_tier_scan() {
+        // Disabled algorithms are fine here: resolution skips them, and the
+        // cache must agree with the scan whichever one it lands on.
+        let tier = |size_range, first| ShardTier {
+            size_range,
+            algorithms: vec![first, fallback_algorithm()],
+        };
+        let config = ShardConfig {
+            tiers: vec![
+                tier(0..=7, ShardAlgorithm::Avx2),
+                tier(8..=63, ShardAlgorithm::Fnv1a),
+                tier(64..=1000, ShardAlgorithm::AesNi),
+            ],
+            ..ShardConfig::default()
+        };
+        let uncached = FastShard::with_config(64, config.clone());
+        let cached = FastShard::with_config(64, config).with_algorithm_cache();
+
+        // Two passes: the first fills slots (with plenty of evictions), the
+        // second reads whatever survived.
+        for _ in 0..2 {
+            for size in (0..2048).chain([usize::MAX >> 8, usize::MAX]) {
+                assert_eq!(
+                    cached.get_algorithm_for_size(size),
+                    uncached.get_algorithm_for_size(size),
+                    "size {}",
+                    size
+                );
+            }
+        }
+    }
+}
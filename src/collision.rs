@@ -0,0 +1,157 @@
+// File: src/collision.rs
+use core::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::mix::splitmix64;
+use crate::FastShard;
+
+/// Wraps a [`FastShard`] and watches for genuine hash collisions: two
+/// different keys producing the same full 64-bit hash (not merely the same
+/// shard). Useful for spotting a weak kernel in production.
+///
+/// Recently seen hashes live in a small direct-mapped table alongside an
+/// independent fingerprint of the key that produced them. A later key with
+/// the same hash but a different fingerprint is counted as a collision.
+/// Entries are evicted by newer hashes mapping to the same slot, so
+/// detection is best-effort.
+#[derive(Debug)]
+pub struct CollisionDetector {
+    shard: FastShard,
+    slots: Mutex<Vec<Option<(u64, u64)>>>,
+    collisions: AtomicU64,
+}
+
+// Fingerprint independent of every `ShardAlgorithm` kernel.
+fn fingerprint(key: &[u8]) -> u64 {
+    #[cfg(feature = "xxh3")]
+    let hash = xxhash_rust::xxh3::xxh3_64_with_seed(key, 0x636f_6c6c_6973_696f);
+    #[cfg(not(feature = "xxh3"))]
+    let hash = key
+        .iter()
+        .fold(0x636f_6c6c_6973_696f_u64, |acc, &byte| splitmix64(acc ^ byte as u64));
+    splitmix64(hash ^ key.len() as u64)
+}
+
+impl FastShard {
+    /// Wraps this instance in a [`CollisionDetector`] remembering up to
+    /// `slots` recent hashes (rounded up to a power of two).
+    pub fn with_collision_detector(self, slots: usize) -> CollisionDetector {
+        let slots = slots.max(1).next_power_of_two();
+        CollisionDetector {
+            shard: self,
+            slots: Mutex::new(vec![None; slots]),
+            collisions: AtomicU64::new(0),
+        }
+    }
+}
+
+impl CollisionDetector {
+    /// Same result as [`FastShard::shard`], recording the key's hash. The
+    /// placement counts toward the inner instance's metrics and observer
+    /// just as `shard` would.
+    pub fn shard(&self, key: &[u8]) -> u32 {
+        let key_len = key.len();
+        let key = self.shard.transform_key(key);
+        // Hashed even for a single shard, which is what gets watched.
+        let hash = self.shard.hash_transformed(&key);
+        self.record(hash, fingerprint(&key));
+        let shard = if self.shard.shard_count == 1 { 0 } else { self.shard.reduce(hash) };
+        self.shard.record(key_len, None, shard);
+        shard
+    }
+
+    /// Number of collisions seen so far.
+    pub fn collisions(&self) -> u64 {
+        self.collisions.load(Ordering::Relaxed)
+    }
+
+    pub fn inner(&self) -> &FastShard {
+        &self.shard
+    }
+
+    fn record(&self, hash: u64, fingerprint: u64) {
+        let mut slots = self.slots.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let idx = (splitmix64(hash) as usize) & (slots.len() - 1);
+        match slots[idx] {
+            Some((seen, seen_fingerprint)) if seen == hash && seen_fingerprint != fingerprint => {
+                self.collisions.fetch_add(1, Ordering::Relaxed);
+            }
+            _ => slots[idx] = Some((hash, fingerprint)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A plain sum of 32-bit lanes, so permuting lanes collides.
+    fn lane_sum(key: &[u8]) -> u64 {
+        key.chunks(4)
+            .map(|lane| {
+                let mut bytes = [0u8; 4];
+                bytes[..lane.len()].copy_from_slice(lane);
+                u32::from_le_bytes(bytes)
+            })
+            .fold(0u32, u32::wrapping_add) as u64
+    }
+
+    #[test]
+    fn test_detects_sum_kernel_collisions() {
+        let weak = FastShard::with_hasher(1024, lane_sum).with_collision_detector(256);
+        let a = [1u8, 0, 0, 0, 2, 0, 0, 0];
+        let b = [2u8, 0, 0, 0, 1, 0, 0, 0];
+
+        weak.shard(&a);
+        weak.shard(&a);
+        assert_eq!(weak.collisions(), 0, "repeating a key is not a collision");
+        assert_eq!(weak.shard(&b), weak.inner().shard(&a));
+        assert_eq!(weak.collisions(), 1);
+
+        let scalar = crate::ShardConfig::builder()
+            .tier_from(0, vec![crate::fallback_algorithm()])
+            .build()
+            .unwrap();
+        let strong = FastShard::with_config(1024, scalar).with_collision_detector(256);
+        strong.shard(&a);
+        strong.shard(&b);
+        assert_eq!(strong.collisions(), 0);
+    }
+
+    #[test]
+    fn test_detector_placements_reach_metrics_and_observer() {
+        let events = std::sync::Arc::new(AtomicU64::new(0));
+        let sink = std::sync::Arc::clone(&events);
+        let detector = FastShard::new(64)
+            .with_metrics()
+            .with_observer(move |_| {
+                sink.fetch_add(1, Ordering::Relaxed);
+            })
+            .with_collision_detector(16);
+        let placed = detector.shard(b"user:42");
+        detector.shard(b"user:43");
+        assert_eq!(detector.inner().shard_counts().iter().sum::<u64>(), 2);
+        assert!(detector.inner().shard_counts()[placed as usize] > 0);
+        assert_eq!(events.load(Ordering::Relaxed), 2);
+
+        let single = FastShard::new(1).with_metrics().with_collision_detector(16);
+        assert_eq!(single.shard(b"user:42"), 0);
+        assert_eq!(single.inner().shard_counts(), [1]);
+    }
+
+    #[test]
+    #[cfg(feature = "avx512")]
+    fn test_avx512_kernel_survives_lane_permutation() {
+        if !crate::cpu::CpuFeatures::detect().avx512f {
+            return;
+        }
+        let config = crate::ShardConfig::builder()
+            .tier_from(0, vec![crate::ShardAlgorithm::Avx512])
+            .build()
+            .unwrap();
+        let detector = FastShard::with_config(1024, config).with_collision_detector(256);
+        detector.shard(&[1u8, 0, 0, 0, 2, 0, 0, 0]);
+        detector.shard(&[2u8, 0, 0, 0, 1, 0, 0, 0]);
+        assert_eq!(detector.collisions(), 0);
+    }
+}
@@ -0,0 +1,75 @@
+// File: src/cpu.rs
+//
+// Runtime CPU feature detection for the SIMD kernels. The kernels are
+// compiled with `#[target_feature(enable = ...)]` whenever their cargo
+// feature is on, whatever the build's `-C target-feature` flags, so a generic
+// binary still runs them on capable hosts. `FastShard` detects once at
+// construction and keeps the answer, so an instance never switches a key
+// between a kernel and its fallback. Runtime detection needs `std`; without
+// it a kernel runs only if the build enables its target feature statically.
+
+use crate::ShardAlgorithm;
+
+/// Which SIMD kernels are both compiled in and supported by this CPU.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct CpuFeatures {
+    pub(crate) avx512f: bool,
+    pub(crate) avx2: bool,
+    pub(crate) aes: bool,
+    /// AArch64 NEON with the ARMv8 AES instructions.
+    pub(crate) neon: bool,
+    /// WebAssembly SIMD128, which is fixed at compile time.
+    pub(crate) simd128: bool,
+    /// SSE4.2, for its CRC-32C instructions.
+    pub(crate) sse42: bool,
+}
+
+impl CpuFeatures {
+    #[cfg(all(target_arch = "x86_64", feature = "std"))]
+    pub(crate) fn detect() -> Self {
+        Self {
+            avx512f: cfg!(feature = "avx512") && is_x86_feature_detected!("avx512f"),
+            avx2: cfg!(feature = "avx2") && is_x86_feature_detected!("avx2"),
+            aes: cfg!(feature = "aesni") && is_x86_feature_detected!("aes"),
+            neon: false,
+            simd128: false,
+            sse42: cfg!(feature = "crc32c") && is_x86_feature_detected!("sse4.2"),
+        }
+    }
+
+    #[cfg(all(target_arch = "aarch64", feature = "std"))]
+    pub(crate) fn detect() -> Self {
+        Self {
+            neon: cfg!(feature = "neon") && std::arch::is_aarch64_feature_detected!("aes"),
+            ..Self::default()
+        }
+    }
+
+    // Without `std` there is no runtime detection; kernels run only when
+    // the build enables their target feature statically.
+    #[cfg(not(all(any(target_arch = "x86_64", target_arch = "aarch64"), feature = "std")))]
+    pub(crate) fn detect() -> Self {
+        Self {
+            avx512f: cfg!(all(target_arch = "x86_64", target_feature = "avx512f", feature = "avx512")),
+            avx2: cfg!(all(target_arch = "x86_64", target_feature = "avx2", feature = "avx2")),
+            aes: cfg!(all(target_arch = "x86_64", target_feature = "aes", feature = "aesni")),
+            neon: cfg!(all(target_arch = "aarch64", target_feature = "aes", feature = "neon")),
+            simd128: cfg!(all(target_arch = "wasm32", target_feature = "simd128", feature = "simd128")),
+            sse42: cfg!(all(target_arch = "x86_64", target_feature = "sse4.2", feature = "crc32c")),
+        }
+    }
+
+    /// Whether `algorithm` runs its own kernel here. Scalar algorithms are
+    /// always usable once compiled in.
+    pub(crate) fn supports(&self, algorithm: &ShardAlgorithm) -> bool {
+        match algorithm {
+            ShardAlgorithm::Avx512 => self.avx512f,
+            ShardAlgorithm::Avx2 => self.avx2,
+            ShardAlgorithm::AesNi => self.aes,
+            ShardAlgorithm::Neon => self.neon,
+            ShardAlgorithm::Simd128 => self.simd128,
+            ShardAlgorithm::Crc32c => self.sse42,
+            other => other.is_enabled(),
+        }
+    }
+}
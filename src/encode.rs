@@ -0,0 +1,64 @@
+// File: src/encode.rs
+//
+// Compact string forms of a key's full 64-bit hash for logging routing
+// decisions. Both are fixed-width, so they sort like the underlying value.
+
+use alloc::format;
+use alloc::string::String;
+
+use crate::FastShard;
+
+const BASE62: &[u8; 62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+// 62^11 > 2^64, so eleven digits always suffice.
+const BASE62_WIDTH: usize = 11;
+
+impl FastShard {
+    /// The key's full hash as 16 lowercase hex digits.
+    pub fn hash_hex(&self, key: &[u8]) -> String {
+        format!("{:016x}", self.hash64(key))
+    }
+
+    /// The key's full hash as 11 base62 digits (`0-9A-Za-z`, most
+    /// significant first).
+    pub fn hash_base62(&self, key: &[u8]) -> String {
+        let mut value = self.hash64(key);
+        let mut digits = [b'0'; BASE62_WIDTH];
+        for digit in digits.iter_mut().rev() {
+            *digit = BASE62[(value % 62) as usize];
+            value /= 62;
+        }
+        digits.iter().map(|&b| b as char).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_base62(s: &str) -> u64 {
+        s.bytes().fold(0u64, |acc, b| {
+            let digit = BASE62.iter().position(|&d| d == b).unwrap() as u64;
+            acc * 62 + digit
+        })
+    }
+
+    #[test]
+    fn test_hash_strings_round_trip() {
+        let shard = FastShard::new(1024);
+        for key in [&b""[..], b"a", b"fast-shard", &[0xff; 300]] {
+            let hash = shard.hash64(key);
+
+            let hex = shard.hash_hex(key);
+            assert_eq!(hex.len(), 16);
+            assert_eq!(u64::from_str_radix(&hex, 16).unwrap(), hash);
+
+            let base62 = shard.hash_base62(key);
+            assert_eq!(base62.len(), BASE62_WIDTH);
+            assert_eq!(decode_base62(&base62), hash);
+        }
+        assert_eq!(
+            FastShard::with_hasher(8, |_| u64::MAX).hash_base62(b"k"),
+            "LygHa16AHYF"
+        );
+    }
+}
@@ -0,0 +1,125 @@
+// File: src/error.rs
+use alloc::string::String;
+use core::fmt;
+use core::ops::RangeInclusive;
+
+use crate::ShardAlgorithm;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShardError {
+    /// An XXH3 secret shorter than [`crate::XXH3_SECRET_SIZE_MIN`] bytes.
+    SecretTooShort { len: usize, min: usize },
+    /// `FastShard::self_check` saw its sample keys collapse onto too few shards.
+    DegenerateDistribution,
+    /// A shard count of zero, which leaves nowhere to place keys.
+    ZeroShardCount,
+    /// The config failed [`crate::ShardConfig::validate`].
+    InvalidConfig(ConfigError),
+    /// A key whose length differs from the config's `fixed_length`.
+    KeyLength { expected: usize, actual: usize },
+}
+
+impl fmt::Display for ShardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShardError::SecretTooShort { len, min } => {
+                write!(f, "xxh3 secret is {} bytes, at least {} are required", len, min)
+            }
+            ShardError::DegenerateDistribution => {
+                write!(f, "sample keys collapsed onto too few shards")
+            }
+            ShardError::ZeroShardCount => write!(f, "shard count must be at least 1"),
+            ShardError::InvalidConfig(err) => write!(f, "invalid shard config: {}", err),
+            ShardError::KeyLength { expected, actual } => {
+                write!(f, "key is {} bytes, the config requires exactly {}", actual, expected)
+            }
+        }
+    }
+}
+
+impl core::error::Error for ShardError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            ShardError::InvalidConfig(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<ConfigError> for ShardError {
+    fn from(err: ConfigError) -> Self {
+        ShardError::InvalidConfig(err)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The config names an algorithm whose cargo feature is disabled.
+    AlgorithmDisabled(ShardAlgorithm),
+    /// A tier whose range contains no sizes (`start > end`).
+    EmptyRange(RangeInclusive<usize>),
+    /// Two tiers cover some of the same sizes.
+    Overlap {
+        first: RangeInclusive<usize>,
+        second: RangeInclusive<usize>,
+    },
+    /// No tier covers these sizes.
+    Gap(RangeInclusive<usize>),
+    /// The tier with this range lists no algorithms.
+    EmptyAlgorithms(RangeInclusive<usize>),
+    /// Config text that did not deserialize: bad syntax, an unknown
+    /// algorithm name or a field of the wrong type. Holds the parser's
+    /// message.
+    Parse(String),
+    /// A config file that could not be read.
+    Read(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::AlgorithmDisabled(algo) => {
+                write!(f, "{:?} is disabled by its cargo feature", algo)
+            }
+            ConfigError::EmptyRange(range) => write!(f, "tier range {:?} is empty", range),
+            ConfigError::Overlap { first, second } => {
+                write!(f, "tier ranges {:?} and {:?} overlap", first, second)
+            }
+            ConfigError::Gap(range) => write!(f, "no tier covers key sizes {:?}", range),
+            ConfigError::EmptyAlgorithms(range) => {
+                write!(f, "tier {:?} lists no algorithms", range)
+            }
+            ConfigError::Parse(message) => write!(f, "malformed config: {}", message),
+            ConfigError::Read(message) => write!(f, "cannot read config: {}", message),
+        }
+    }
+}
+
+impl core::error::Error for ConfigError {}
+
+/// The error from parsing a [`ShardAlgorithm`] with [`str::parse`]: the name
+/// matched no algorithm.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseAlgorithmError {
+    pub(crate) name: String,
+}
+
+impl ParseAlgorithmError {
+    /// The name that failed to parse.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl fmt::Display for ParseAlgorithmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown shard algorithm \"{}\"; expected one of ", self.name)?;
+        for (i, algorithm) in crate::info::ALGORITHMS.iter().enumerate() {
+            let separator = if i == 0 { "" } else { ", " };
+            write!(f, "{}{}", separator, algorithm)?;
+        }
+        Ok(())
+    }
+}
+
+impl core::error::Error for ParseAlgorithmError {}
@@ -0,0 +1,205 @@
+// File: src/golden.rs
+//
+// Acceptance-test helper for downstream CI: record `(key, shard)` pairs once,
+// then re-check them after upgrading the crate or changing build flags.
+
+use alloc::vec::Vec;
+
+use crate::FastShard;
+
+/// A golden entry whose key no longer lands on its recorded shard.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// Position of the entry in the golden slice.
+    pub index: usize,
+    pub key: Vec<u8>,
+    pub expected: u32,
+    pub actual: u32,
+}
+
+impl FastShard {
+    /// Re-shards every golden key and returns each entry whose shard
+    /// differs, in golden order.
+    pub fn verify_against_golden(&self, golden: &[(Vec<u8>, u32)]) -> Result<(), Vec<Mismatch>> {
+        let mismatches: Vec<Mismatch> = golden
+            .iter()
+            .enumerate()
+            .filter_map(|(index, (key, expected))| {
+                let actual = self.shard(key);
+                (actual != *expected).then(|| Mismatch {
+                    index,
+                    key: key.clone(),
+                    expected: *expected,
+                    actual,
+                })
+            })
+            .collect();
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(mismatches)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ShardAlgorithm, ShardConfig};
+
+    // Key `i` is `i` bytes long, so the keys cover every XXH3 size class
+    // (0, 1..=3, 4..=8, 9..=16, 17..=128, 129..=240, >240) and every SIMD
+    // chunk tail. The formula is fixed here on purpose; do not "simplify" it.
+    fn frozen_keys() -> Vec<Vec<u8>> {
+        (0..256usize)
+            .map(|i| (0..i).map(|j| ((i * 131 + j * 197) % 251) as u8).collect())
+            .collect()
+    }
+
+    const FROZEN_SHARD_COUNTS: [u32; 5] = [1, 7, 1024, 1_000_003, u32::MAX];
+
+    fn single_algorithm(count: u32, algorithm: ShardAlgorithm) -> FastShard {
+        let config = ShardConfig::single(algorithm);
+        FastShard::with_config(count, config)
+    }
+
+    // XXH3-64 (seed 0, default secret) of each frozen key, recorded once.
+    // Placement is `hash % shard_count`. If this test fails, placements
+    // have moved for every deployed XXH3 user: fix the regression, never
+    // the table.
+    #[cfg(feature = "xxh3")]
+    #[rustfmt::skip]
+    const FROZEN_XXH3: [u64; 256] = [
+        0x2d06_8005_38d3_94c2, 0x5bdd_1392_69f4_47c1, 0xe7b0_1424_12cf_5300, 0x156c_f1eb_f39e_92a3,
+        0xf5f5_74e0_33dd_ca2d, 0x7676_8c24_8358_597f, 0xe683_b671_d73b_f659, 0x9898_0d24_7a11_456e,
+        0x0f05_12a3_817c_89fb, 0x73ac_d218_2ff5_2047, 0x5276_1ddd_9e19_f96a, 0xd75c_ee1d_6758_5917,
+        0x5281_4736_3b22_a044, 0x4785_5ce5_2e77_bf33, 0xab1f_d497_0e5d_40c2, 0x7bd3_9277_ce62_5901,
+        0x6883_5e59_28a4_dd67, 0xa735_8d05_9515_6a3f, 0xf027_972e_d7c4_811e, 0x4b51_fb1d_839c_3c59,
+        0xc250_686c_c8b1_6418, 0xf295_774d_9c94_d93f, 0xfc1d_76e4_32fa_f1ed, 0x199b_8d7c_d8b5_30b3,
+        0x6faa_6a4e_8475_4823, 0x29c7_0fd5_630f_d2be, 0x3ed4_160b_dd8d_e046, 0xe896_725b_4a36_3724,
+        0x3c47_ce6d_0b74_550b, 0x9cd9_de77_e7b2_05cc, 0xca1c_46a2_9f65_54ce, 0x41da_f7a5_2414_16ae,
+        0x253e_7619_7791_df49, 0xca90_1288_4513_fb83, 0x4794_29dd_96c6_9a0a, 0x651c_7fbe_1a0a_5374,
+        0xc07c_4be4_fc57_022b, 0x2a72_fdf5_1e53_4953, 0x02ae_13b9_c4ad_3d52, 0x231e_c6e5_53dd_1950,
+        0xfbf4_ebe5_5bb5_1c2e, 0x7758_2bd1_b83b_3a4b, 0xde33_58d0_fc19_587e, 0xfb4c_2717_4ca9_d44e,
+        0x59ad_3b10_e436_416c, 0xe9bb_926d_6c2f_ddfb, 0xd4e8_be8b_e40e_f0ea, 0xa4ef_1e44_cb31_ec0b,
+        0x8e81_dcc0_5040_c0c6, 0x323b_81a7_1493_d0b4, 0x7570_a20d_405a_9d59, 0xd8a7_ca8c_489d_65e7,
+        0xebbc_c0ae_bf72_02ed, 0xae24_15b6_0451_bd26, 0x7588_42a9_9115_4b22, 0x6648_7fbc_e030_2ac3,
+        0xd5fb_a57a_a685_ace0, 0xa92f_3a66_9380_0a6d, 0xd027_b06c_993d_2091, 0xb7d2_e7d4_4850_5cc3,
+        0xe7d8_9004_dc4e_799a, 0x7d33_ccd7_6a47_0853, 0x0f99_5bb5_da39_693d, 0x39ef_7b00_8706_b3d9,
+        0xf5e9_2d53_b535_a3fb, 0xcd10_56cf_b266_f4f2, 0x4dd1_a7fe_8c21_c0f9, 0x4c03_5ceb_785f_b471,
+        0x391e_8a3c_6458_1277, 0xbe03_9628_7451_3684, 0x555c_5aed_eee7_2dd1, 0x1cbc_a9d6_7b17_bcd8,
+        0x5dc0_4b61_16ef_517b, 0x473b_acd0_2e54_7449, 0xe856_9baf_1c19_82dc, 0xdbed_79c8_f863_ae5b,
+        0x6fd8_a358_c508_63e8, 0xc41a_7939_c115_9ebc, 0xae86_6272_d172_3558, 0x0cf7_be38_a299_3f2c,
+        0x7906_e2ff_4af8_cf2e, 0x654b_b60a_5599_4105, 0x0bdf_2a87_f5b3_39b9, 0x9502_445a_70e0_a83c,
+        0x23a9_9856_c3cc_97ca, 0x3c37_acbc_7bee_cbed, 0x2e96_5ee4_493a_3bea, 0xfd71_bd30_0e9c_cb55,
+        0x5cce_3d68_f5a8_91a1, 0xfe98_f3af_9b8d_3f88, 0x1391_b136_3e15_410d, 0xec73_b1c9_ff96_151a,
+        0x8889_152c_6006_50c1, 0xdf55_a293_8d84_6f84, 0xb632_71ab_9ff1_47cf, 0xb718_01ff_16b5_5235,
+        0x17d2_e4d1_647e_854f, 0x336a_f113_5d26_531e, 0x522c_ef2c_93ce_effb, 0xaf96_9f7f_745e_5502,
+        0x525f_db52_37bf_32ad, 0x8aeb_06f7_7f6f_444c, 0x6ed9_149f_09bc_c90b, 0xd608_b1c6_83bf_0cbd,
+        0x10db_b110_4923_ca7c, 0xd3ff_f2c2_dc47_c8c2, 0x7d39_0bd6_d412_abd1, 0x8dc0_c3af_612b_4bdc,
+        0xe300_89b0_05cd_65f9, 0xd6b0_34b5_67e9_3943, 0xd25b_ff96_8aef_f588, 0x6a32_beb5_5011_4e0e,
+        0x5c93_3634_7d2c_43b3, 0xd5d6_cb49_5a82_8797, 0x0d9b_a585_636b_7713, 0xf5a7_1092_5c6a_58d2,
+        0x5cbe_f3c3_631a_9f48, 0xe1d1_6071_0744_b6de, 0x40b1_dba8_2570_6f36, 0xdaec_c037_515f_483f,
+        0x23bd_7f3d_edaf_60dd, 0xa077_34f4_791f_af9f, 0x1766_fac7_bc45_a73b, 0x85b4_8f72_902f_2d77,
+        0x5b17_952b_1ceb_33b6, 0x6e31_960a_5402_a47c, 0xa172_a406_aa9d_401e, 0xd765_9e7b_96f0_45f1,
+        0x75a7_9b16_2873_f826, 0x24f5_764d_607b_3d7d, 0x2df3_bff9_7faa_6992, 0x601b_d99c_72b2_25b3,
+        0xd126_ebfe_0cf2_09eb, 0x115b_8b8f_10ec_1e08, 0x40b5_aebe_8aaf_8452, 0x2eec_a0da_41ea_e01c,
+        0x21fe_6170_b9d8_67e0, 0x7070_2eb4_c994_40c2, 0x9ce3_a9e4_b758_a118, 0xdd71_4b42_5ae0_9973,
+        0x599b_5638_a99b_f01f, 0x84a9_36da_75e8_397a, 0x3f65_626f_c0c5_037f, 0x05d2_c95d_2829_7f7b,
+        0x3fba_318f_6c7e_79bf, 0x6fa3_cf5f_4221_553a, 0x99ba_35a1_c616_b907, 0x80e2_5fb5_ea1b_61ad,
+        0x6480_4968_89e9_d5dd, 0xee8b_9e6a_4a61_1fb9, 0x9413_f063_7e84_e9e9, 0x2b39_f054_7633_ec37,
+        0x8ab0_633c_c6dd_618b, 0x8530_2f07_abdd_31ab, 0xcc44_bab0_5e10_02ba, 0x3fd2_55a4_a20e_8737,
+        0x4642_5e8e_d7a9_2dcc, 0x0f55_c8f3_6009_9135, 0x0b60_52dd_95ed_32d6, 0x7415_d8fc_668b_856b,
+        0x48bf_39cc_5f75_37a1, 0xecc2_d6f1_be1c_8dca, 0x7f07_d3fb_3d36_7735, 0x1c8b_a3e3_8ae3_55e1,
+        0xb54b_a614_b2d3_e1b9, 0x34e5_6821_836d_533a, 0xd422_3eb0_894e_e66b, 0x7f58_2aaf_8fb5_8572,
+        0x6497_0c19_782d_aefc, 0x42b7_00ea_371e_b569, 0x011b_92c9_b89d_a4f4, 0x830e_63d7_774a_c78b,
+        0xfed2_3f8b_7dd1_0977, 0xd3ab_1b6d_edbf_c29d, 0x2217_0ecc_2a01_e146, 0x442f_9c75_3205_d54f,
+        0x8cf6_a9bf_7fba_5667, 0x95a2_a2da_eeba_3e93, 0x41b1_6bec_12cc_c3b0, 0x23a9_faa4_10fb_e32f,
+        0xcb78_1ea3_b7d9_4693, 0x8b52_4a56_eaf7_0d0b, 0xbdb6_6ed3_438d_3bee, 0xa94f_ea4f_4d0f_e79a,
+        0x9fee_7f1c_619c_b490, 0xaad5_6051_6e39_6183, 0x2dc7_8655_b61c_d8f3, 0x97f5_26f1_6213_e8a9,
+        0x803d_9cc8_ac6b_7522, 0xc4bd_ce99_63d0_6af9, 0x9522_d7ff_62ae_479c, 0x9e4c_6785_77b2_100f,
+        0x7f81_d9ae_955f_340e, 0x0ac6_578a_242a_cd6e, 0xebcd_6ea9_9153_748b, 0x8ef4_593c_c3b7_e580,
+        0x2e16_0873_5c84_8af8, 0x40ea_c9a7_5db1_e3c5, 0xc1c0_0380_3a47_ef79, 0x5a8c_dd36_c75c_ad49,
+        0x1514_355b_02b9_163c, 0x7eee_4bab_a548_d535, 0xc96a_b67a_00f4_c8ad, 0x1378_ea6b_b0e7_b127,
+        0x8d14_19b7_35d6_3329, 0x674f_dc9b_faa5_07b7, 0x0e1e_b3e4_c9df_5be4, 0xb588_4399_4784_ea92,
+        0xd2b6_b3d5_bc2a_9556, 0xe15e_46b6_cc5f_d57c, 0x0de8_cf9c_533d_eadc, 0xefa6_4273_b2ff_788a,
+        0x0f63_f4da_b42f_08a3, 0xf2a2_fad1_f1c8_98f8, 0x5b91_a85f_5499_472c, 0x8d95_53e2_affd_4718,
+        0x0399_2084_9342_99b4, 0xc445_9b12_f81d_77ec, 0xc2c9_8cdb_d717_0e4c, 0xcf2f_a0ec_565c_4682,
+        0x59e4_d2c8_97ab_0fce, 0x8bd5_fcd2_4def_32d2, 0xf029_a4f6_3493_9a60, 0x1fe1_9c85_aacf_2594,
+        0x894e_6779_a7d7_4df7, 0x448a_1d33_b11d_ae5f, 0x4f75_ab9a_2f16_f520, 0x522d_8661_a8b1_365e,
+        0x4e6f_4970_7989_6656, 0x4005_6324_e4bc_25fd, 0xf4e6_9d53_2271_0b34, 0x4cb5_fa7f_0188_8c77,
+        0x041e_cc6a_3d4f_c4b2, 0xd419_1b17_5ad4_0e9f, 0x6ff1_e04a_c7ad_1f3b, 0x0c84_5e3b_d2a2_576a,
+        0x27dd_fb13_6a3e_cde4, 0x6230_eb3c_a20f_d85e, 0x0beb_9eab_1bd0_c9d2, 0xb8e2_ab1f_20d4_cff7,
+        0xd4ec_ea51_6d92_f7cf, 0xa635_7397_2f03_f455, 0x1b52_52e2_070b_8923, 0xc27d_36eb_82b4_3bd8,
+        0x69fb_4fe2_5518_4da5, 0x79fa_735d_0aef_85e9, 0x38c1_a76d_a688_5dbb, 0x02bb_6662_1588_9bc8,
+        0x569b_a3e9_124e_7e69, 0x3a18_0e49_bbda_224f, 0xfd6e_11dd_ada6_ef3a, 0x5b53_96e6_f1b9_0b43,
+        0x85fe_28c3_664b_009c, 0xe8b4_0449_bdd2_58e4, 0xcc9b_41e8_001c_5b64, 0xcf92_47b5_62f8_b57f,
+    ];
+
+    #[test]
+    #[cfg(feature = "xxh3")]
+    fn test_xxh3_placements_are_frozen() {
+        let keys = frozen_keys();
+        for count in FROZEN_SHARD_COUNTS {
+            let golden: Vec<(Vec<u8>, u32)> = keys
+                .iter()
+                .zip(FROZEN_XXH3)
+                .map(|(key, hash)| (key.clone(), (hash % count as u64) as u32))
+                .collect();
+            let shard = single_algorithm(count, ShardAlgorithm::Xxh3);
+            assert_eq!(shard.verify_against_golden(&golden), Ok(()), "{} shards", count);
+        }
+    }
+
+    // FNV-1a is small enough to carry an independent implementation.
+    #[test]
+    #[cfg(feature = "fnv")]
+    fn test_fnv1a_placements_match_frozen_reference() {
+        fn fnv1a(key: &[u8]) -> u64 {
+            key.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+                (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+            })
+        }
+        let keys = frozen_keys();
+        for count in FROZEN_SHARD_COUNTS {
+            let golden: Vec<(Vec<u8>, u32)> =
+                keys.iter().map(|key| (key.clone(), (fnv1a(key) % count as u64) as u32)).collect();
+            let shard = single_algorithm(count, ShardAlgorithm::Fnv1a);
+            assert_eq!(shard.verify_against_golden(&golden), Ok(()), "{} shards", count);
+        }
+    }
+
+    #[test]
+    fn test_verify_against_golden() {
+        let shard = FastShard::new(1024);
+        let mut golden: Vec<(Vec<u8>, u32)> = (0..100)
+            .map(|i| {
+                let key = format!("golden-{}", i).into_bytes();
+                let placed = shard.shard(&key);
+                (key, placed)
+            })
+            .collect();
+        assert_eq!(shard.verify_against_golden(&golden), Ok(()));
+
+        golden[3].1 = (golden[3].1 + 1) % 1024;
+        golden[42].1 = (golden[42].1 + 7) % 1024;
+        let mismatches = shard.verify_against_golden(&golden).unwrap_err();
+        assert_eq!(
+            mismatches,
+            vec![
+                Mismatch {
+                    index: 3,
+                    key: b"golden-3".to_vec(),
+                    expected: golden[3].1,
+                    actual: shard.shard(b"golden-3"),
+                },
+                Mismatch {
+                    index: 42,
+                    key: b"golden-42".to_vec(),
+                    expected: golden[42].1,
+                    actual: shard.shard(b"golden-42"),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,141 @@
+// File: src/hierarchy.rs
+//
+// Two-level placement, e.g. regions then nodes. The node is chosen by the
+// inner instance over the key prefixed with its region id (see
+// `FastShard::shard_with_prefix`), so each region spreads its keys over its
+// nodes independently, and a key's node depends only on its region and the
+// inner instance, never on how many regions there are.
+//
+// Also nested bucket paths for sharded directory trees, one bucket per level.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::mix::splitmix64;
+use crate::FastShard;
+
+/// Places a key on an outer shard (a region), then on an inner shard (a
+/// node) within it.
+#[derive(Debug)]
+pub struct HierarchicalShard {
+    pub outer: FastShard,
+    pub inner: FastShard,
+}
+
+impl HierarchicalShard {
+    pub fn new(outer: FastShard, inner: FastShard) -> Self {
+        Self { outer, inner }
+    }
+
+    /// `(region, node)` for `key`: the region is `outer.shard(key)` and the
+    /// node is `inner`'s placement of `key` salted with that region.
+    pub fn shard2(&self, key: &[u8]) -> (u32, u32) {
+        let region = self.outer.shard(key);
+        (region, self.node(region, key))
+    }
+
+    /// Like [`shard2`](Self::shard2), but picks the region with
+    /// [`FastShard::jump_shard`], so adding a region moves only the keys
+    /// that land in it and every other key keeps both its region and node.
+    pub fn jump_shard2(&self, key: &[u8]) -> (u32, u32) {
+        let region = self.outer.jump_shard(key);
+        (region, self.node(region, key))
+    }
+
+    fn node(&self, region: u32, key: &[u8]) -> u32 {
+        self.inner.shard_with_prefix(&region.to_le_bytes(), key)
+    }
+}
+
+impl FastShard {
+    /// Nested bucket path for `key`, one component per entry of `levels`
+    /// (its bucket count), e.g. `"04/17"` for `levels = [64, 64]`. All
+    /// components derive from one key hash; each is zero-padded to the width
+    /// of its level's largest index. Intended for sharded directory trees.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any level has zero buckets.
+    pub fn shard_path_string(&self, key: &[u8], levels: &[u32]) -> String {
+        assert!(levels.iter().all(|&count| count > 0), "every level needs at least one bucket");
+        let hash = self.hash64(key);
+        levels
+            .iter()
+            .enumerate()
+            .map(|(depth, &count)| {
+                let bucket = splitmix64(hash.wrapping_add(depth as u64)) % count as u64;
+                let width = (count - 1).to_string().len();
+                format!("{:0width$}", bucket, width = width)
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nodes_survive_region_growth() {
+        let before = HierarchicalShard::new(FastShard::new(4), FastShard::new(16));
+        let after = HierarchicalShard::new(FastShard::new(5), FastShard::new(16));
+        let keys = 20_000u32;
+        let mut stayed = 0;
+        for i in 0..keys {
+            let key = format!("key-{}", i);
+            let (old_region, old_node) = before.jump_shard2(key.as_bytes());
+            let (new_region, new_node) = after.jump_shard2(key.as_bytes());
+            assert!(old_region < 4 && new_region < 5 && old_node < 16 && new_node < 16);
+            if old_region == new_region {
+                assert_eq!(old_node, new_node, "{}", key);
+                stayed += 1;
+            } else {
+                assert_eq!(new_region, 4);
+            }
+            // Without jump hashing fewer keys stay, but those that do keep
+            // their node just the same.
+            let (old_region, old_node) = before.shard2(key.as_bytes());
+            let (new_region, new_node) = after.shard2(key.as_bytes());
+            if old_region == new_region {
+                assert_eq!(old_node, new_node, "{}", key);
+            }
+        }
+        let fraction = stayed as f64 / keys as f64;
+        assert!((fraction - 0.8).abs() < 0.02, "{} stayed", fraction);
+    }
+
+    #[test]
+    fn test_regions_pick_nodes_independently() {
+        // Same region and node counts: without the salt every key would sit
+        // on node == region.
+        let shard = HierarchicalShard::new(FastShard::new(8), FastShard::new(8));
+        let mut pairs = [[0u32; 8]; 8];
+        for i in 0..16_000u32 {
+            let (region, node) = shard.shard2(&i.to_le_bytes());
+            pairs[region as usize][node as usize] += 1;
+        }
+        // About 250 keys per (region, node) cell.
+        assert!(pairs.iter().flatten().all(|&n| (150..350).contains(&n)), "{:?}", pairs);
+    }
+
+    #[test]
+    fn test_shard_path_string() {
+        let shard = FastShard::new(64);
+        let levels = [64, 1000, 7];
+        for i in 0..500 {
+            let key = format!("file-{}", i);
+            let path = shard.shard_path_string(key.as_bytes(), &levels);
+            assert_eq!(path, shard.shard_path_string(key.as_bytes(), &levels));
+
+            let parts: Vec<&str> = path.split('/').collect();
+            assert_eq!(parts.len(), levels.len());
+            for (part, &count) in parts.iter().zip(levels.iter()) {
+                assert_eq!(part.len(), (count - 1).to_string().len());
+                assert!(part.parse::<u32>().unwrap() < count);
+            }
+        }
+        assert_eq!(shard.shard_path_string(b"k", &[]), "");
+    }
+}
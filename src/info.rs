@@ -0,0 +1,201 @@
+// File: src/info.rs
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+use crate::cpu::CpuFeatures;
+use crate::{FastShard, ParseAlgorithmError, ShardAlgorithm};
+
+// Every `ShardAlgorithm` variant, in declaration order.
+pub(crate) const ALGORITHMS: [ShardAlgorithm; 11] = [
+    ShardAlgorithm::Avx512,
+    ShardAlgorithm::Avx2,
+    ShardAlgorithm::AesNi,
+    ShardAlgorithm::Neon,
+    ShardAlgorithm::Simd128,
+    ShardAlgorithm::Crc32c,
+    ShardAlgorithm::Fnv1a,
+    ShardAlgorithm::Xxh3,
+    ShardAlgorithm::Wyhash,
+    ShardAlgorithm::SipHash13,
+    ShardAlgorithm::StdDefault,
+];
+
+/// How well an algorithm withstands structured or adversarial keys. Only a
+/// `Keyed` algorithm with a secret seed stops whoever controls the keys from
+/// piling them onto one shard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashStrength {
+    /// Linear lane arithmetic; keys crafted to offset lanes collide.
+    Weak,
+    /// Well distributed for ordinary keys, but not collision resistant.
+    NonCryptographic,
+    /// A keyed PRF: placement is unpredictable without the seed, so
+    /// hash-flooding needs the seed to stay secret.
+    Keyed,
+}
+
+/// What tooling needs to present one [`ShardAlgorithm`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlgorithmInfo {
+    pub algorithm: ShardAlgorithm,
+    /// Short lowercase name, matching the cargo feature where there is one.
+    pub name: &'static str,
+    /// Runs on SIMD or AES instructions rather than scalar code.
+    pub hardware_accelerated: bool,
+    /// Compiled in and, for hardware-accelerated algorithms, supported by
+    /// this CPU; otherwise configs naming it fall back.
+    pub available: bool,
+    pub strength: HashStrength,
+}
+
+/// Metadata for every [`ShardAlgorithm`] variant, available or not, in
+/// declaration order.
+pub fn algorithm_info() -> Vec<AlgorithmInfo> {
+    let cpu = CpuFeatures::detect();
+    ALGORITHMS
+        .into_iter()
+        .map(|algorithm| {
+            let (hardware_accelerated, strength) = match algorithm {
+                ShardAlgorithm::Avx512 => (true, HashStrength::NonCryptographic),
+                ShardAlgorithm::Avx2 => (true, HashStrength::NonCryptographic),
+                ShardAlgorithm::AesNi => (true, HashStrength::NonCryptographic),
+                ShardAlgorithm::Neon => (true, HashStrength::NonCryptographic),
+                ShardAlgorithm::Simd128 => (true, HashStrength::NonCryptographic),
+                ShardAlgorithm::Crc32c => (true, HashStrength::Weak),
+                ShardAlgorithm::Fnv1a => (false, HashStrength::NonCryptographic),
+                ShardAlgorithm::Xxh3 => (false, HashStrength::NonCryptographic),
+                ShardAlgorithm::Wyhash => (false, HashStrength::NonCryptographic),
+                ShardAlgorithm::SipHash13 => (false, HashStrength::Keyed),
+                ShardAlgorithm::StdDefault => (false, HashStrength::NonCryptographic),
+            };
+            AlgorithmInfo {
+                available: cpu.supports(&algorithm),
+                name: algorithm.name(),
+                algorithm,
+                hardware_accelerated,
+                strength,
+            }
+        })
+        .collect()
+}
+
+/// The algorithms that run their own implementation on this build and host,
+/// in declaration order. Configs naming any other algorithm fall back.
+pub fn capabilities() -> Vec<ShardAlgorithm> {
+    let cpu = CpuFeatures::detect();
+    ALGORITHMS.into_iter().filter(|algorithm| cpu.supports(algorithm)).collect()
+}
+
+impl FastShard {
+    /// The algorithms this CPU and build can run; the same list as
+    /// [`capabilities`].
+    pub fn supported_algorithms() -> Vec<ShardAlgorithm> {
+        capabilities()
+    }
+}
+
+impl ShardAlgorithm {
+    // The serde name, which `Display` also prints.
+    fn name(&self) -> &'static str {
+        match self {
+            ShardAlgorithm::Avx512 => "avx512",
+            ShardAlgorithm::Avx2 => "avx2",
+            ShardAlgorithm::AesNi => "aesni",
+            ShardAlgorithm::Neon => "neon",
+            ShardAlgorithm::Simd128 => "simd128",
+            ShardAlgorithm::Crc32c => "crc32c",
+            ShardAlgorithm::Fnv1a => "fnv1a",
+            ShardAlgorithm::Xxh3 => "xxh3",
+            ShardAlgorithm::Wyhash => "wyhash",
+            ShardAlgorithm::SipHash13 => "siphash13",
+            ShardAlgorithm::StdDefault => "std-default",
+        }
+    }
+}
+
+/// Prints the algorithm's lowercase name, the same string serde uses.
+impl fmt::Display for ShardAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Parses the names `Display` prints, ignoring ASCII case, plus the
+/// aliases `"aes-ni"` and `"fnv"`, for algorithms read from CLI flags or
+/// environment variables.
+impl FromStr for ShardAlgorithm {
+    type Err = ParseAlgorithmError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let alias = match s.to_ascii_lowercase().as_str() {
+            "aes-ni" => Some(ShardAlgorithm::AesNi),
+            "fnv" => Some(ShardAlgorithm::Fnv1a),
+            _ => None,
+        };
+        alias
+            .or_else(|| {
+                ALGORITHMS.into_iter().find(|algorithm| s.eq_ignore_ascii_case(algorithm.name()))
+            })
+            .ok_or_else(|| ParseAlgorithmError { name: s.to_string() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A new variant fails to compile here until it is added to `ALGORITHMS`.
+    fn listed(algorithm: &ShardAlgorithm) -> bool {
+        match algorithm {
+            ShardAlgorithm::Avx512
+            | ShardAlgorithm::Avx2
+            | ShardAlgorithm::AesNi
+            | ShardAlgorithm::Neon
+            | ShardAlgorithm::Simd128
+            | ShardAlgorithm::Crc32c
+            | ShardAlgorithm::Fnv1a
+            | ShardAlgorithm::Xxh3
+            | ShardAlgorithm::Wyhash
+            | ShardAlgorithm::SipHash13
+            | ShardAlgorithm::StdDefault => ALGORITHMS.contains(algorithm),
+        }
+    }
+
+    #[test]
+    fn test_algorithm_info_covers_every_variant() {
+        let info = algorithm_info();
+        assert_eq!(info.len(), ALGORITHMS.len());
+        for (entry, algorithm) in info.iter().zip(ALGORITHMS.iter()) {
+            assert!(listed(algorithm));
+            assert_eq!(&entry.algorithm, algorithm);
+        }
+
+        let available: Vec<ShardAlgorithm> =
+            info.iter().filter(|entry| entry.available).map(|entry| entry.algorithm.clone()).collect();
+        assert_eq!(available, capabilities());
+        assert_eq!(FastShard::supported_algorithms(), capabilities());
+        assert!(capabilities().contains(&crate::fallback_algorithm()));
+        for entry in &info {
+            assert!(!entry.available || entry.algorithm.is_enabled(), "{}", entry.name);
+        }
+    }
+
+    #[test]
+    fn test_algorithm_names_round_trip() {
+        for algorithm in ALGORITHMS {
+            let name = algorithm.to_string();
+            assert_eq!(name.parse::<ShardAlgorithm>(), Ok(algorithm.clone()));
+            assert_eq!(name.to_ascii_uppercase().parse::<ShardAlgorithm>(), Ok(algorithm));
+        }
+        assert_eq!("AES-NI".parse(), Ok(ShardAlgorithm::AesNi));
+        assert_eq!("Fnv".parse(), Ok(ShardAlgorithm::Fnv1a));
+
+        let err = "md5".parse::<ShardAlgorithm>().unwrap_err();
+        assert_eq!(err.name(), "md5");
+        assert!(err.to_string().contains("\"md5\""), "{}", err);
+        assert!(err.to_string().contains("xxh3"), "{}", err);
+        assert!(" xxh3".parse::<ShardAlgorithm>().is_err());
+    }
+}
@@ -0,0 +1,113 @@
+// File: src/jump.rs
+//
+// Jump consistent hashing (Lamping & Veach, "A Fast, Minimal Memory,
+// Consistent Hash Algorithm"). A key's bucket only ever moves to a newly
+// added bucket as the bucket count grows.
+
+use alloc::vec::Vec;
+
+use crate::FastShard;
+
+const JUMP_MULTIPLIER: u64 = 2862933555777941757;
+
+// Successive buckets `hash` jumps to as the bucket count grows without
+// bound: the key sits in each until the count passes the next one.
+fn jumps(mut hash: u64) -> impl Iterator<Item = u64> {
+    let mut next = 0u64;
+    core::iter::from_fn(move || {
+        let bucket = next;
+        hash = hash.wrapping_mul(JUMP_MULTIPLIER).wrapping_add(1);
+        next = ((bucket + 1) as f64 * ((1u64 << 31) as f64 / ((hash >> 33) + 1) as f64)) as u64;
+        Some(bucket)
+    })
+}
+
+// Bucket of `hash` among `buckets` buckets.
+pub(crate) fn jump_hash(hash: u64, buckets: u32) -> u32 {
+    jumps(hash).take_while(|&bucket| bucket < buckets as u64).last().unwrap_or(0) as u32
+}
+
+impl FastShard {
+    /// Shards `key` with jump consistent hashing over the full key hash:
+    /// O(log shard_count) and allocation-free. Growing the shard count from
+    /// `n` to `n + 1` moves only the keys that land on the new shard, about
+    /// `1 / (n + 1)` of them.
+    pub fn jump_shard(&self, key: &[u8]) -> u32 {
+        jump_hash(self.hash64(key), self.shard_count)
+    }
+
+    /// The shard counts `n` in `2..=max_count` at which `key`'s jump-hash
+    /// placement changes, i.e. `jump(key, n) != jump(key, n - 1)`, in
+    /// increasing order. At each one the key moves to the new shard `n - 1`.
+    /// Jump hashing moves a key about `ln(max_count)` times in total.
+    pub fn jump_breakpoints(&self, key: &[u8], max_count: u32) -> Vec<u32> {
+        jumps(self.hash64(key))
+            .skip(1)
+            .map(|bucket| bucket + 1)
+            .take_while(|&count| count <= max_count as u64)
+            .map(|count| count as u32)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jump_hash_reference_values() {
+        // Computed with an independent port of the paper's C++ code.
+        assert_eq!(jump_hash(0, 1), 0);
+        assert_eq!(jump_hash(0xdead_beef, 1), 0);
+        assert_eq!(jump_hash(1, 100), 55);
+        assert_eq!(jump_hash(256, 1024), 520);
+        assert_eq!(jump_hash(0xdead_beef, 1000), 285);
+        assert_eq!(jump_hash(12_345_678_901_234_567, 100_000), 46958);
+        assert_eq!(jump_hash(0x7fff_ffff_ffff_ffff, 1 << 20), 622_539);
+    }
+
+    #[test]
+    fn test_jump_shard_growth_moves_one_in_n_plus_one() {
+        let before = FastShard::new(100);
+        let after = FastShard::new(101);
+        let keys = 100_000u32;
+        let mut moved = 0;
+        for i in 0..keys {
+            let key = format!("key-{}", i);
+            let old = before.jump_shard(key.as_bytes());
+            let new = after.jump_shard(key.as_bytes());
+            assert!(old < 100 && new < 101);
+            if old != new {
+                // Only the new bucket takes keys.
+                assert_eq!(new, 100);
+                moved += 1;
+            }
+        }
+        let fraction = moved as f64 / keys as f64;
+        assert!((fraction - 1.0 / 101.0).abs() < 0.002, "moved {}", fraction);
+    }
+
+    #[test]
+    fn test_jump_breakpoints_match_jump_hash() {
+        let shard = FastShard::new(1);
+        for i in 0..200u32 {
+            let key = format!("key-{}", i);
+            let hash = shard.hash64(key.as_bytes());
+            let breakpoints = shard.jump_breakpoints(key.as_bytes(), 500);
+            assert!(breakpoints.windows(2).all(|w| w[0] < w[1]));
+
+            let expected: Vec<u32> = (2..=500)
+                .filter(|&n| jump_hash(hash, n) != jump_hash(hash, n - 1))
+                .collect();
+            assert_eq!(breakpoints, expected, "{}", key);
+            for &n in &breakpoints {
+                assert_eq!(jump_hash(hash, n), n - 1);
+            }
+            for n in [2, 17, 100, 499, 500] {
+                let moved = jump_hash(hash, n) != jump_hash(hash, n - 1);
+                assert_eq!(breakpoints.contains(&n), moved);
+                assert_eq!(FastShard::new(n).jump_shard(key.as_bytes()), jump_hash(hash, n));
+            }
+        }
+    }
+}
@@ -1,223 +1,1346 @@
 // File: src/lib.rs
-use std::ops::RangeInclusive;
-#[cfg(all(target_arch = "x86_64", any(target_feature = "avx512f", target_feature = "avx2", target_feature = "aes")))]
-use std::arch::x86_64::*;
+//
+// `no_std` (with `alloc`) unless the `std` feature is on; tests always have
+// `std`.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
-#[derive(Debug, Clone, PartialEq)]
+extern crate alloc;
+
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::RangeInclusive;
+
+#[cfg(feature = "std")]
+mod adaptive;
+#[cfg(feature = "std")]
+mod analysis;
+#[cfg(feature = "std")]
+mod auto;
+mod batch;
+mod builder;
+mod cache;
+#[cfg(feature = "std")]
+mod collision;
+mod cpu;
+mod encode;
+mod error;
+#[cfg(any(test, feature = "test-util"))]
+mod golden;
+mod hierarchy;
+mod info;
+mod jump;
+mod load;
+mod maglev;
+mod metrics;
+mod migration;
+mod mix;
+#[cfg(all(target_arch = "aarch64", feature = "neon"))]
+mod neon;
+mod normalize;
+mod observe;
+mod reduce;
+#[cfg(any(
+    test,
+    all(
+        target_arch = "x86_64",
+        any(feature = "avx512", feature = "avx2", feature = "aesni", feature = "crc32c")
+    ),
+    all(target_arch = "aarch64", feature = "neon"),
+    all(target_arch = "wasm32", target_feature = "simd128", feature = "simd128")
+))]
+mod reference;
+mod rendezvous;
+mod rolling;
+mod routing;
+mod shadow;
+#[cfg(feature = "siphash")]
+mod siphash;
+mod state;
+mod token;
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128", feature = "simd128"))]
+mod wasm;
+#[cfg(all(
+    target_arch = "x86_64",
+    any(feature = "avx512", feature = "avx2", feature = "aesni", feature = "crc32c")
+))]
+mod simd;
+
+#[cfg(feature = "std")]
+pub use analysis::{DistributionStats, TierTuning};
+pub use batch::ShardScratch;
+pub use builder::ShardConfigBuilder;
+#[cfg(feature = "std")]
+pub use collision::CollisionDetector;
+pub use error::{ConfigError, ParseAlgorithmError, ShardError};
+#[cfg(feature = "test-util")]
+pub use golden::Mismatch;
+pub use hierarchy::HierarchicalShard;
+pub use info::{algorithm_info, capabilities, AlgorithmInfo, HashStrength};
+pub use maglev::MaglevShard;
+pub use mix::Finalizer;
+pub use normalize::NormalizationPolicy;
+pub use observe::{ShardEvent, ShardObserver};
+pub use reduce::ReductionMode;
+pub use rolling::{RollTrigger, RollingShard};
+pub use routing::Route;
+pub use shadow::ShadowShard;
+pub use state::{ShardHasher, ShardHasherState};
+pub use token::ShardToken;
+
+#[cfg(not(any(feature = "xxh3", feature = "fnv")))]
+compile_error!("fast-shard needs at least one of the `xxh3` or `fnv` features");
+
+/// Largest key size (in bytes) served by the default config's small-key tier.
+pub const DEFAULT_SMALL_KEY_MAX: usize = 16;
+
+/// Smallest secret XXH3 accepts for keyed hashing.
+#[cfg(feature = "xxh3")]
+pub const XXH3_SECRET_SIZE_MIN: usize = 136;
+
+/// With the `serde` feature, algorithms serialize as lowercase names
+/// (`"avx512"`, `"avx2"`, `"aesni"`, `"neon"`, `"simd128"`, `"crc32c"`,
+/// `"fnv1a"`, `"xxh3"`, `"wyhash"`, `"siphash13"`, `"std-default"`). The same
+/// names come out of `Display` and go into `FromStr`, which also ignores
+/// case and accepts `"aes-ni"` and `"fnv"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum ShardAlgorithm {
     Avx512,
     Avx2,
+    /// AES-NI: each 16-byte chunk is absorbed with two AES rounds keyed from
+    /// the seed, and two more finalize the state. Falls back to XXH3
+    /// without the AES extension.
     AesNi,
+    /// AArch64 NEON with the ARMv8 AES instructions. Computes the same hash
+    /// as `AesNi`, so the two place keys identically; on other architectures,
+    /// or CPUs without the AES extension, it falls back to XXH3.
+    Neon,
+    /// WebAssembly SIMD128, for wasm32 builds with `+simd128`. Computes the
+    /// same hash as `Avx512`, so the two place keys identically. WASM SIMD
+    /// is fixed at compile time; without it this falls back to XXH3.
+    Simd128,
+    /// Hardware CRC-32C (SSE4.2 `crc32`), 8 bytes per instruction. Very cheap
+    /// for short fixed-size keys, but CRC is linear, so structured keys can
+    /// collide as with `Avx2`. Falls back to XXH3 without SSE4.2.
+    Crc32c,
     Fnv1a,
     Xxh3,
+    /// Wyhash (final version 1, via the `wyhash` crate). Faster than XXH3 on
+    /// short keys in scalar code, so a good pick for a small-key tier on
+    /// hosts without SIMD. Without the `wyhash` feature it falls back to XXH3.
+    Wyhash,
+    /// Keyed SipHash-1-3 with the config's `seed` as its key. Where an
+    /// attacker picks the keys, this stops them from flooding one shard so
+    /// long as the seed stays secret; see [`ShardConfig::secure`]. Slower
+    /// than the other scalar hashes. Without the `siphash` feature it falls
+    /// back to XXH3.
+    SipHash13,
+    /// `std`'s `DefaultHasher` (SipHash-1-3 with zero keys) over the key as
+    /// a `[u8]`, i.e. a length prefix then the bytes, exactly as
+    /// `key.hash(&mut DefaultHasher::new())`. Reproduces placements from code
+    /// that bucketed with `DefaultHasher` during a migration. Its output is
+    /// not guaranteed stable across Rust releases, so do not use it for
+    /// placements that must outlive a toolchain upgrade.
+    #[cfg_attr(feature = "serde", serde(rename = "std-default"))]
+    StdDefault,
+}
+
+impl ShardAlgorithm {
+    /// Whether this algorithm's kernel is compiled in, i.e. its cargo
+    /// feature (`avx512`, `avx2`, `aesni`, `neon`, `simd128`, `crc32c`, `fnv`,
+    /// `xxh3`, `wyhash`, `siphash` or, for `StdDefault`, `std`) is enabled.
+    pub fn is_enabled(&self) -> bool {
+        match self {
+            ShardAlgorithm::Avx512 => cfg!(feature = "avx512"),
+            ShardAlgorithm::Avx2 => cfg!(feature = "avx2"),
+            ShardAlgorithm::AesNi => cfg!(feature = "aesni"),
+            ShardAlgorithm::Neon => cfg!(feature = "neon"),
+            ShardAlgorithm::Simd128 => cfg!(feature = "simd128"),
+            ShardAlgorithm::Crc32c => cfg!(feature = "crc32c"),
+            ShardAlgorithm::Fnv1a => cfg!(feature = "fnv"),
+            ShardAlgorithm::Xxh3 => cfg!(feature = "xxh3"),
+            ShardAlgorithm::Wyhash => cfg!(feature = "wyhash"),
+            ShardAlgorithm::SipHash13 => cfg!(feature = "siphash"),
+            ShardAlgorithm::StdDefault => cfg!(feature = "std"),
+        }
+    }
+}
+
+#[cfg(feature = "fnv")]
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+#[cfg(feature = "fnv")]
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+// 64-bit FNV-1a, inlined rather than going through `fnv::FnvHasher` and the
+// `Hasher` trait; for seed 0, bit-identical to `FnvHasher::write` + `finish`.
+#[cfg(feature = "fnv")]
+#[inline]
+fn fnv1a_64(key: &[u8], seed: u64) -> u64 {
+    fnv1a_64_update(fnv1a_basis(seed), key)
+}
+
+// FNV-1a's starting state for `seed`; the standard offset basis for seed 0.
+#[cfg(feature = "fnv")]
+fn fnv1a_basis(seed: u64) -> u64 {
+    FNV_OFFSET_BASIS ^ mix::fmix64(seed)
 }
 
-#[derive(Debug, Clone)]
+// Continues an FNV-1a hash over `bytes`, for streaming callers.
+#[cfg(feature = "fnv")]
+#[inline]
+fn fnv1a_64_update(mut hash: u64, bytes: &[u8]) -> u64 {
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+// Used when nothing in an algorithm list is usable on this build.
+fn fallback_algorithm() -> ShardAlgorithm {
+    if cfg!(feature = "xxh3") {
+        ShardAlgorithm::Xxh3
+    } else {
+        ShardAlgorithm::Fnv1a
+    }
+}
+
+/// With the `serde` feature, `size_range` serializes as
+/// `{ "start": 0, "end": 16 }` (both bounds inclusive). An `end` of
+/// `usize::MAX` is left out, and a missing `end` reads as `usize::MAX`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ShardTier {
+    #[cfg_attr(feature = "serde", serde(with = "load::size_range"))]
     pub size_range: RangeInclusive<usize>,
+    /// Tried in order: the first one compiled in and, for SIMD kernels,
+    /// supported by the CPU (detected at construction) serves the tier.
     pub algorithms: Vec<ShardAlgorithm>,
 }
 
-#[derive(Debug, Clone)]
+/// How a key size picks among tiers whose ranges contain it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum TierSelection {
+    /// The first containing tier in `tiers` order.
+    #[default]
+    FirstMatch,
+    /// The containing tier with the narrowest range; ties go to the earlier
+    /// tier. Lets a narrow tier override part of a wider one.
+    NarrowestMatch,
+}
+
+/// With the `serde` feature, fields missing from a serialized config take
+/// their [`ShardConfig::default`] values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct ShardConfig {
     pub tiers: Vec<ShardTier>,
     pub default_algorithms: Vec<ShardAlgorithm>,
+    /// Mix the shard count into the hash before the modulo, so placements
+    /// for N and 2N shards are decorrelated instead of sharing low bits.
+    /// This deliberately breaks the "subset" property consistent-hash
+    /// schemes such as jump hashing rely on; it only applies to the modulo
+    /// mapping.
+    pub salt_with_shard_count: bool,
+    /// Key normalization applied before hashing; the default leaves keys as is.
+    pub normalization: NormalizationPolicy,
+    /// Which containing tier serves a size when tiers overlap.
+    pub tier_selection: TierSelection,
+    /// Declares that every key is exactly this many bytes (e.g. 16 for
+    /// UUIDs), so the length carries no information and is left out of the
+    /// hash: the SIMD kernels (AVX-512, AVX2, AES-NI, NEON, SIMD128) skip
+    /// their final length fold and hash the bytes alone, matching bytes-only
+    /// schemes elsewhere, and [`FastShard::shard_skip_prefix`] skips its
+    /// length-mixing step. XXH3, FNV-1a, wyhash, SipHash and CRC-32C already
+    /// hash exactly as their standard definitions and are unchanged;
+    /// `StdDefault` keeps the length prefix that `Hash for [u8]` writes.
+    /// [`FastShard::try_shard`] rejects other lengths and `shard`
+    /// debug-asserts them.
+    pub fixed_length: Option<usize>,
+    /// Right-pads keys shorter than this with zero bytes before hashing,
+    /// for interop with schemes that hash at least N bytes. A padded key's
+    /// true length is mixed into its hash, so `b"a"` and `b"a\0"` still
+    /// differ, as does a short key from a genuine full-length key with the
+    /// same bytes. Keys already this long hash unchanged. The empty key is
+    /// padded like any other: it hashes as `min_key_len` zeros mixed with
+    /// length 0, not as the algorithm's empty-input hash.
+    pub min_key_len: Option<usize>,
+    /// Mixing step applied to the 64-bit hash before it is mapped onto a
+    /// shard (and before `salt_with_shard_count`). Defaults to none.
+    pub finalizer: Finalizer,
+    /// How the final hash is mapped onto a shard index. Defaults to
+    /// [`ReductionMode::Modulo`]; changing it moves keys.
+    pub reduction: ReductionMode,
+    /// Seeds every algorithm, so instances that differ only in seed place
+    /// the same keys independently (e.g. a table and a dedup filter that
+    /// should not share hot spots). Seed 0, the default, is the unseeded
+    /// placement. XXH3 and Wyhash take it as their seed, SipHash-1-3 as its
+    /// key, FNV-1a, AES-NI and CRC-32C mix it into their initial state,
+    /// AVX-512 and AVX2 into their running hash and `StdDefault` hashes it
+    /// ahead of the key. Within one 32-byte AVX2 chunk the lane sum is
+    /// linear, so keys whose chunks collide there collide under every seed.
+    /// A [`FastShard::with_xxh3_secret`] secret replaces the seed for XXH3.
+    pub seed: u64,
+}
+
+impl ShardConfig {
+    /// A config that hashes every key size with `algorithm`: one tier over
+    /// `0..=usize::MAX`, with `algorithm` as the default too. Where the
+    /// algorithm is unavailable, keys fall back as with any other config.
+    pub fn single(algorithm: ShardAlgorithm) -> Self {
+        ShardConfig {
+            tiers: vec![ShardTier {
+                size_range: 0..=usize::MAX,
+                algorithms: vec![algorithm.clone()],
+            }],
+            default_algorithms: vec![algorithm],
+            ..ShardConfig::default()
+        }
+    }
+
+    /// A config that hashes every key size with XXH3 (seed 0, default
+    /// secret) and nothing else, bypassing SIMD selection. In this mode
+    /// `shard(key)` for a given shard count is the same on every
+    /// architecture, CPU and build, and is guaranteed stable across crate
+    /// versions; use it wherever independently deployed producers and
+    /// consumers must agree on placement. Options layered on top (such as
+    /// `normalization` or a `finalizer`) are applied the same everywhere too.
+    #[cfg(feature = "xxh3")]
+    pub fn deterministic() -> Self {
+        ShardConfig::single(ShardAlgorithm::Xxh3)
+    }
+
+    /// A config for keys an attacker may choose: every key size is hashed
+    /// with [`ShardAlgorithm::SipHash13`] keyed by `seed`. Draw the seed from
+    /// a secure random source and keep it secret (and stable, since changing
+    /// it moves every key); anyone who learns it can collide keys again.
+    #[cfg(feature = "siphash")]
+    pub fn secure(seed: u64) -> Self {
+        ShardConfig {
+            seed,
+            ..ShardConfig::single(ShardAlgorithm::SipHash13)
+        }
+    }
+
+    /// A config that picks the cheapest kernel per key size. Keys of up to
+    /// [`DEFAULT_SMALL_KEY_MAX`] bytes try the SIMD and AES kernels (AVX-512,
+    /// AVX2, AES-NI, NEON, SIMD128), then Wyhash, XXH3 and FNV-1a. Longer
+    /// keys go straight to XXH3. CPUs without an instruction set fall
+    /// through to the next entry, so the config runs everywhere.
+    /// Distribution is that of the chosen kernels: the SIMD lane sums are
+    /// linear within a chunk (see [`algorithm_info`]), so prefer
+    /// [`quality_optimized`](Self::quality_optimized) for keys whose
+    /// structure an adversary or a sequential generator controls.
+    pub fn latency_optimized() -> Self {
+        let small = [
+            ShardAlgorithm::Avx512,
+            ShardAlgorithm::Avx2,
+            ShardAlgorithm::AesNi,
+            ShardAlgorithm::Neon,
+            ShardAlgorithm::Simd128,
+            ShardAlgorithm::Wyhash,
+            ShardAlgorithm::Xxh3,
+            ShardAlgorithm::Fnv1a,
+        ];
+        let large = [ShardAlgorithm::Xxh3, ShardAlgorithm::Wyhash, ShardAlgorithm::Fnv1a];
+        Self::two_tier(&small, &large)
+    }
+
+    /// A config that favours distribution over speed: XXH3 for every key
+    /// size, with the `fmix64` finalizer so even the FNV-1a fallback of a
+    /// build without `xxh3` avalanches fully. It never uses the SIMD lane
+    /// sums or CRC-32C, whose collisions follow key structure, and
+    /// costs about a nanosecond more than
+    /// [`latency_optimized`](Self::latency_optimized) on short keys. It does
+    /// not resist chosen keys; see [`ShardConfig::secure`] for that.
+    pub fn quality_optimized() -> Self {
+        let algorithms = [ShardAlgorithm::Xxh3, ShardAlgorithm::Wyhash, ShardAlgorithm::Fnv1a];
+        ShardConfig {
+            finalizer: Finalizer::Fmix64,
+            ..Self::two_tier(&algorithms, &algorithms)
+        }
+    }
+
+    // The default tier split with the listed algorithms of `small` and
+    // `large`, and the first listed large-key algorithm as the default.
+    fn two_tier(small: &[ShardAlgorithm], large: &[ShardAlgorithm]) -> Self {
+        let available = |algorithms: &[ShardAlgorithm]| -> Vec<ShardAlgorithm> {
+            algorithms.iter().filter(|algo| listed(algo)).cloned().collect()
+        };
+        let large = available(large);
+        ShardConfig {
+            tiers: vec![
+                ShardTier {
+                    size_range: 0..=DEFAULT_SMALL_KEY_MAX,
+                    algorithms: available(small),
+                },
+                ShardTier {
+                    size_range: DEFAULT_SMALL_KEY_MAX + 1..=usize::MAX,
+                    algorithms: large.clone(),
+                },
+            ],
+            default_algorithms: large,
+            ..ShardConfig::default()
+        }
+    }
+
+    /// Checks that every tier has a non-empty range and at least one
+    /// algorithm, that the tiers together cover every key size from 0 to
+    /// `usize::MAX`, that no two tiers overlap (unless tiers are selected by
+    /// [`TierSelection::NarrowestMatch`], where nesting is the point), and
+    /// that every algorithm the config names is compiled in.
+    /// [`FastShard::try_with_config`] runs it before constructing.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        for tier in &self.tiers {
+            if tier.size_range.is_empty() {
+                return Err(ConfigError::EmptyRange(tier.size_range.clone()));
+            }
+            if tier.algorithms.is_empty() {
+                return Err(ConfigError::EmptyAlgorithms(tier.size_range.clone()));
+            }
+        }
+        if self.tier_selection == TierSelection::FirstMatch {
+            self.check_no_overlap()?;
+        }
+        self.check_no_gaps()?;
+        let referenced = self
+            .tiers
+            .iter()
+            .flat_map(|tier| tier.algorithms.iter())
+            .chain(self.default_algorithms.iter());
+        for algo in referenced {
+            if !algo.is_enabled() {
+                return Err(ConfigError::AlgorithmDisabled(algo.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    // Reports the first key size range no tier covers.
+    fn check_no_gaps(&self) -> Result<(), ConfigError> {
+        let mut ranges: Vec<&RangeInclusive<usize>> =
+            self.tiers.iter().map(|tier| &tier.size_range).collect();
+        ranges.sort_by_key(|range| *range.start());
+        // First size not yet covered; `None` once `usize::MAX` is.
+        let mut next = Some(0usize);
+        for range in ranges {
+            let Some(expected) = next else { break };
+            if *range.start() > expected {
+                return Err(ConfigError::Gap(expected..=*range.start() - 1));
+            }
+            next = range.end().checked_add(1).map(|after| after.max(expected));
+        }
+        match next {
+            Some(start) => Err(ConfigError::Gap(start..=usize::MAX)),
+            None => Ok(()),
+        }
+    }
+
+    fn check_no_overlap(&self) -> Result<(), ConfigError> {
+        let mut ranges: Vec<&RangeInclusive<usize>> = self
+            .tiers
+            .iter()
+            .map(|tier| &tier.size_range)
+            .filter(|range| !range.is_empty())
+            .collect();
+        ranges.sort_by_key(|range| *range.start());
+        for pair in ranges.windows(2) {
+            if pair[1].start() <= pair[0].end() {
+                return Err(ConfigError::Overlap {
+                    first: pair[0].clone(),
+                    second: pair[1].clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+// Whether a preset should list `algo`: compiled in and, for NEON and
+// SIMD128, on their own architectures; elsewhere they would always fall back.
+fn listed(algo: &ShardAlgorithm) -> bool {
+    match algo {
+        ShardAlgorithm::Neon => cfg!(target_arch = "aarch64") && algo.is_enabled(),
+        ShardAlgorithm::Simd128 => cfg!(target_arch = "wasm32") && algo.is_enabled(),
+        _ => algo.is_enabled(),
+    }
 }
 
 impl Default for ShardConfig {
     fn default() -> Self {
-        let small_key_algorithms = vec![
+        let small_key_algorithms = [
             ShardAlgorithm::Avx512,
             ShardAlgorithm::Avx2,
             ShardAlgorithm::AesNi,
+            ShardAlgorithm::Neon,
+            ShardAlgorithm::Simd128,
             ShardAlgorithm::Fnv1a,
             ShardAlgorithm::Xxh3,
-        ];
+        ]
+        .into_iter()
+        .filter(listed)
+        .collect();
 
-        let large_key_algorithms = vec![
+        let large_key_algorithms = [
             ShardAlgorithm::Avx512,
             ShardAlgorithm::Avx2,
             ShardAlgorithm::AesNi,
+            ShardAlgorithm::Neon,
+            ShardAlgorithm::Simd128,
             ShardAlgorithm::Xxh3,
             ShardAlgorithm::Fnv1a,
-        ];
+        ]
+        .into_iter()
+        .filter(listed)
+        .collect();
 
         ShardConfig {
             tiers: vec![
                 ShardTier {
-                    size_range: 0..=16,
+                    size_range: 0..=DEFAULT_SMALL_KEY_MAX,
                     algorithms: small_key_algorithms,
                 },
                 ShardTier {
-                    size_range: 17..=usize::MAX,
+                    size_range: DEFAULT_SMALL_KEY_MAX + 1..=usize::MAX,
                     algorithms: large_key_algorithms,
                 },
             ],
-            default_algorithms: vec![ShardAlgorithm::Xxh3],
+            default_algorithms: vec![fallback_algorithm()],
+            salt_with_shard_count: false,
+            normalization: NormalizationPolicy::default(),
+            tier_selection: TierSelection::default(),
+            fixed_length: None,
+            min_key_len: None,
+            finalizer: Finalizer::None,
+            reduction: ReductionMode::Modulo,
+            seed: 0,
         }
     }
 }
 
-#[derive(Debug)]
+/// A caller-supplied hash function that replaces algorithm selection.
+pub type CustomHasher = Arc<dyn Fn(&[u8]) -> u64 + Send + Sync>;
+
+/// A caller-supplied key normalization applied before any hashing.
+pub type KeyTransform = Arc<dyn for<'a> Fn(&'a [u8]) -> Cow<'a, [u8]> + Send + Sync>;
+
 pub struct FastShard {
     shard_count: u32,
+    reducer: reduce::Reducer,
     config: ShardConfig,
+    #[cfg(feature = "xxh3")]
+    xxh3_secret: Option<Vec<u8>>,
+    custom_hasher: Option<CustomHasher>,
+    key_transform: Option<KeyTransform>,
+    algorithm_cache: Option<Box<cache::AlgorithmCache>>,
+    metrics: Option<Box<metrics::ShardMetrics>>,
+    observer: Option<ShardObserver>,
+    #[cfg(feature = "std")]
+    adaptive: Option<Box<adaptive::AdaptiveKernels>>,
+    // Per-shard weights from `with_weights`, replacing `reducer`.
+    #[cfg(feature = "std")]
+    weights: Option<Arc<[u32]>>,
+    // SIMD support detected at construction.
+    cpu: cpu::CpuFeatures,
+}
+
+impl fmt::Debug for FastShard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut s = f.debug_struct("FastShard");
+        s.field("shard_count", &self.shard_count);
+        s.field("config", &self.config);
+        #[cfg(feature = "xxh3")]
+        s.field("xxh3_secret", &self.xxh3_secret.as_ref().map(|_| "<secret>"));
+        s.field("custom_hasher", &self.custom_hasher.as_ref().map(|_| "<fn>"));
+        s.field("key_transform", &self.key_transform.as_ref().map(|_| "<fn>"));
+        s.field("algorithm_cache", &self.algorithm_cache.is_some());
+        s.field("metrics", &self.metrics.is_some());
+        s.field("observer", &self.observer.as_ref().map(|_| "<fn>"));
+        s.field("adaptive", &self.has_adaptive_kernels());
+        #[cfg(feature = "std")]
+        s.field("weights", &self.weights);
+        s.field("cpu", &self.cpu);
+        s.finish()
+    }
+}
+
+// Diverse sample for `self_check`: varied leading bytes and lengths spanning
+// every default tier and SIMD chunk size.
+fn self_check_keys() -> Vec<Vec<u8>> {
+    (0..64u32)
+        .map(|i| {
+            let mut key = i.wrapping_mul(0x9E37_79B9).to_le_bytes().to_vec();
+            key.extend((0..i * 3).map(|j| (j as u8) ^ (i as u8)));
+            key
+        })
+        .collect()
 }
 
 impl FastShard {
+    /// # Panics
+    ///
+    /// `shard_count` must be nonzero. Debug builds panic here; release
+    /// builds panic on the first `shard` call. Use [`FastShard::try_new`]
+    /// for counts that come from input.
     pub fn new(shard_count: u32) -> Self {
+        Self::with_config(shard_count, ShardConfig::default())
+    }
+
+    /// Like [`FastShard::new`], but returns [`ShardError::ZeroShardCount`]
+    /// for a zero `shard_count` instead of panicking later.
+    pub fn try_new(shard_count: u32) -> Result<Self, ShardError> {
+        Self::try_with_config(shard_count, ShardConfig::default())
+    }
+
+    /// Uses `config` as is; overlapping tiers resolve per `tier_selection`
+    /// and uncovered sizes fall through to `default_algorithms`. See
+    /// [`FastShard::try_with_config`] to reject such configs instead.
+    ///
+    /// # Panics
+    ///
+    /// Like [`FastShard::new`], on a zero `shard_count`.
+    pub fn with_config(shard_count: u32, config: ShardConfig) -> Self {
+        debug_assert!(shard_count > 0, "shard_count must be nonzero; use FastShard::try_new");
         Self {
             shard_count,
-            config: ShardConfig::default(),
+            reducer: reduce::Reducer::new(shard_count, config.reduction),
+            config,
+            #[cfg(feature = "xxh3")]
+            xxh3_secret: None,
+            custom_hasher: None,
+            key_transform: None,
+            algorithm_cache: None,
+            metrics: None,
+            observer: None,
+            #[cfg(feature = "std")]
+            adaptive: None,
+            #[cfg(feature = "std")]
+            weights: None,
+            cpu: cpu::CpuFeatures::detect(),
         }
     }
 
-    pub fn with_config(shard_count: u32, config: ShardConfig) -> Self {
-        Self { shard_count, config }
+    /// Like [`FastShard::with_config`], but fails on a zero shard count or a
+    /// config that [`ShardConfig::validate`] rejects.
+    pub fn try_with_config(shard_count: u32, config: ShardConfig) -> Result<Self, ShardError> {
+        Self::try_from((shard_count, config))
+    }
+
+    /// Shards every key with `hasher` instead of the configured algorithms.
+    pub fn with_hasher<F>(shard_count: u32, hasher: F) -> Self
+    where
+        F: Fn(&[u8]) -> u64 + Send + Sync + 'static,
+    {
+        let mut shard = Self::new(shard_count);
+        shard.custom_hasher = Some(Arc::new(hasher));
+        shard
+    }
+
+    /// Runs every key through `transform` before hashing, on every sharding
+    /// path, so keys that normalize to the same bytes always shard together.
+    /// Return `Cow::Borrowed` for keys that need no change to avoid copying.
+    pub fn with_key_transform<F>(mut self, transform: F) -> Self
+    where
+        F: for<'a> Fn(&'a [u8]) -> Cow<'a, [u8]> + Send + Sync + 'static,
+    {
+        self.key_transform = Some(Arc::new(transform));
+        self
+    }
+
+    /// The number of shards keys are placed on.
+    pub fn shard_count(&self) -> u32 {
+        self.shard_count
+    }
+
+    /// Changes the number of shards in place; later calls place keys as an
+    /// instance built with `count` and the same config, secret and key hooks
+    /// would. Most keys move under the default modulo mapping (see
+    /// [`FastShard::migration_plan`]). Metrics, if enabled, restart from zero
+    /// with one counter per new shard. Weights from
+    /// [`FastShard::with_weights`] are dropped unless `count` is unchanged,
+    /// since they are given per shard.
+    ///
+    /// Fails with [`ShardError::ZeroShardCount`] for a zero `count`, leaving
+    /// the instance as it was.
+    pub fn set_shard_count(&mut self, count: u32) -> Result<(), ShardError> {
+        if count == 0 {
+            return Err(ShardError::ZeroShardCount);
+        }
+        if count == self.shard_count {
+            return Ok(());
+        }
+        self.shard_count = count;
+        self.reducer = reduce::Reducer::new(count, self.config.reduction);
+        if self.metrics.is_some() {
+            self.metrics = Some(Box::new(metrics::ShardMetrics::new(count)));
+        }
+        #[cfg(feature = "std")]
+        {
+            self.weights = None;
+        }
+        Ok(())
+    }
+
+    /// Hashes a built-in set of diverse keys and fails with
+    /// [`ShardError::DegenerateDistribution`] if they collapse onto too few
+    /// shards, e.g. because a kernel ignores its input.
+    pub fn self_check(&self) -> Result<(), ShardError> {
+        if self.shard_count < 2 {
+            return Ok(());
+        }
+        let keys = self_check_keys();
+        let mut seen = vec![false; self.shard_count as usize];
+        // Not through `shard`: synthetic keys must not show up in metrics.
+        for key in &keys {
+            seen[self.reduce(self.hash64(key)) as usize] = true;
+        }
+        let distinct = seen.iter().filter(|&&hit| hit).count();
+        let required = (keys.len().min(self.shard_count as usize) / 4).max(2);
+        if distinct < required {
+            return Err(ShardError::DegenerateDistribution);
+        }
+        Ok(())
+    }
+
+    /// Routes every key size through XXH3 keyed with a custom secret
+    /// (`xxh3_64_with_secret`). The secret must be at least
+    /// [`XXH3_SECRET_SIZE_MIN`] bytes.
+    #[cfg(feature = "xxh3")]
+    pub fn with_xxh3_secret(shard_count: u32, secret: Vec<u8>) -> Result<Self, ShardError> {
+        if secret.len() < XXH3_SECRET_SIZE_MIN {
+            return Err(ShardError::SecretTooShort {
+                len: secret.len(),
+                min: XXH3_SECRET_SIZE_MIN,
+            });
+        }
+        let config = ShardConfig::single(ShardAlgorithm::Xxh3);
+        let mut shard = Self::with_config(shard_count, config);
+        shard.xxh3_secret = Some(secret);
+        Ok(shard)
     }
 
     fn get_available_algorithm(&self, algorithms: &[ShardAlgorithm]) -> ShardAlgorithm {
         for algo in algorithms {
             match algo {
-                ShardAlgorithm::Avx512 => {
-                    #[cfg(target_feature = "avx512f")]
-                    return ShardAlgorithm::Avx512;
+                ShardAlgorithm::Avx512
+                | ShardAlgorithm::Avx2
+                | ShardAlgorithm::AesNi
+                | ShardAlgorithm::Neon
+                | ShardAlgorithm::Simd128
+                | ShardAlgorithm::Crc32c => {
+                    if self.cpu.supports(algo) {
+                        return algo.clone();
+                    }
+                }
+                ShardAlgorithm::Fnv1a => {
+                    #[cfg(feature = "fnv")]
+                    return ShardAlgorithm::Fnv1a;
                 }
-                ShardAlgorithm::Avx2 => {
-                    #[cfg(target_feature = "avx2")]
-                    return ShardAlgorithm::Avx2;
+                ShardAlgorithm::Xxh3 => {
+                    #[cfg(feature = "xxh3")]
+                    return ShardAlgorithm::Xxh3;
                 }
-                ShardAlgorithm::AesNi => {
-                    #[cfg(target_feature = "aes")]
-                    return ShardAlgorithm::AesNi;
+                ShardAlgorithm::Wyhash => {
+                    #[cfg(feature = "wyhash")]
+                    return ShardAlgorithm::Wyhash;
+                }
+                ShardAlgorithm::SipHash13 => {
+                    #[cfg(feature = "siphash")]
+                    return ShardAlgorithm::SipHash13;
+                }
+                ShardAlgorithm::StdDefault => {
+                    #[cfg(feature = "std")]
+                    return ShardAlgorithm::StdDefault;
                 }
-                ShardAlgorithm::Fnv1a => return ShardAlgorithm::Fnv1a,
-                ShardAlgorithm::Xxh3 => return ShardAlgorithm::Xxh3,
             }
         }
-        ShardAlgorithm::Xxh3 // Final fallback
+        fallback_algorithm()
+    }
+
+    // Index of the tier serving `size` under the config's `tier_selection`,
+    // or `None` for the defaults.
+    fn tier_index(&self, size: usize) -> Option<usize> {
+        let mut containing = self
+            .config
+            .tiers
+            .iter()
+            .enumerate()
+            .filter(|(_, tier)| tier.size_range.contains(&size));
+        match self.config.tier_selection {
+            TierSelection::FirstMatch => containing.next().map(|(idx, _)| idx),
+            TierSelection::NarrowestMatch => containing
+                .min_by_key(|(_, tier)| tier.size_range.end() - tier.size_range.start())
+                .map(|(idx, _)| idx),
+        }
+    }
+
+    fn get_algorithm_for_tier(&self, tier: Option<usize>) -> ShardAlgorithm {
+        match tier {
+            Some(idx) => self.get_available_algorithm(&self.config.tiers[idx].algorithms),
+            None => self.get_available_algorithm(&self.config.default_algorithms),
+        }
     }
 
     fn get_algorithm_for_size(&self, size: usize) -> ShardAlgorithm {
-        for tier in &self.config.tiers {
-            if tier.size_range.contains(&size) {
-                return self.get_available_algorithm(&tier.algorithms);
-            }
+        let Some(cache) = &self.algorithm_cache else {
+            return self.get_algorithm_for_tier(self.tier_index(size));
+        };
+        if let Some(algorithm) = cache.get(size) {
+            return algorithm;
         }
-        self.get_available_algorithm(&self.config.default_algorithms)
+        let algorithm = self.get_algorithm_for_tier(self.tier_index(size));
+        cache.insert(size, &algorithm);
+        algorithm
+    }
+
+    /// The algorithm keys of `key_len` bytes are hashed with on this build
+    /// and host: the first usable entry of the serving tier, with SIMD
+    /// kernels subject to the CPU detection done at construction. Ignores a
+    /// [`FastShard::with_hasher`] closure.
+    /// [`supported_algorithms`](Self::supported_algorithms) lists every
+    /// algorithm the host runs, so a preferred algorithm missing from it
+    /// explains a downgrade.
+    pub fn which_algorithm(&self, key_len: usize) -> ShardAlgorithm {
+        self.get_algorithm_for_size(key_len)
     }
 
     pub fn shard(&self, key: &[u8]) -> u32 {
-        let algorithm = self.get_algorithm_for_size(key.len());
-        match algorithm {
-            ShardAlgorithm::Avx512 => self.shard_with_avx512(key),
-            ShardAlgorithm::Avx2 => self.shard_with_avx2(key),
-            ShardAlgorithm::AesNi => self.shard_with_aesni(key),
-            ShardAlgorithm::Fnv1a => self.shard_with_fnv1a(key),
-            ShardAlgorithm::Xxh3 => self.shard_with_xxh3(key),
+        debug_assert!(
+            self.config.fixed_length.is_none_or(|len| len == key.len()),
+            "key length differs from the config's fixed_length"
+        );
+        // A single shard is the answer for every key; skip the hash entirely.
+        let shard = if self.shard_count == 1 {
+            0
+        } else {
+            self.reduce(self.hash64(key))
+        };
+        self.record(key.len(), None, shard);
+        shard
+    }
+
+    /// [`shard`](Self::shard) as a `u16`, for indexes that store shard ids
+    /// in two bytes.
+    ///
+    /// # Panics
+    ///
+    /// If `shard_count` exceeds 65536, when some shard ids would not fit.
+    pub fn shard16(&self, key: &[u8]) -> u16 {
+        assert!(
+            self.shard_count <= u16::MAX as u32 + 1,
+            "shard_count {} has ids past u16::MAX",
+            self.shard_count
+        );
+        self.shard(key) as u16
+    }
+
+    /// Shards `key` onto `0..shard_count` for partition spaces wider than
+    /// `u32`, independently of this instance's own shard count. Uses the
+    /// same hash, finalizer and [`ReductionMode`] as [`shard`](Self::shard),
+    /// so it equals `shard(key) as u64` when `shard_count` matches and no
+    /// weights are set. Weights and metrics do not apply. The reciprocal is
+    /// not cached across calls, so each call pays one division.
+    ///
+    /// # Panics
+    ///
+    /// On a zero `shard_count`.
+    pub fn shard64(&self, key: &[u8], shard_count: u64) -> u64 {
+        assert!(shard_count > 0, "shard_count must be nonzero");
+        let hash = self.prepare(self.hash64(key), shard_count);
+        reduce::Reducer::new(shard_count, self.config.reduction).reduce(hash)
+    }
+
+    /// Like [`shard`](Self::shard), but checks the key against the config's
+    /// `fixed_length` and returns [`ShardError::KeyLength`] on a mismatch.
+    pub fn try_shard(&self, key: &[u8]) -> Result<u32, ShardError> {
+        match self.config.fixed_length {
+            Some(expected) if expected != key.len() => Err(ShardError::KeyLength {
+                expected,
+                actual: key.len(),
+            }),
+            _ => Ok(self.shard(key)),
         }
     }
 
-    #[cfg(target_feature = "avx512f")]
-    fn shard_with_avx512(&self, key: &[u8]) -> u32 {
-        unsafe {
-            if is_x86_feature_detected!("avx512f") {
-                let mut hash = 0u32;
-                for chunk in key.chunks(64) {
-                    let vec = if chunk.len() == 64 {
-                        _mm512_loadu_si512(chunk.as_ptr() as *const _)
-                    } else {
-                        let mut padded = [0u8; 64];
-                        padded[..chunk.len()].copy_from_slice(chunk);
-                        _mm512_loadu_si512(padded.as_ptr() as *const _)
-                    };
-                    
-                    let reduced = _mm512_reduce_add_epi32(vec);
-                    hash = hash.wrapping_add(reduced as u32);
+    /// [`FastShard::hash64`] and [`FastShard::shard`] of `key` from a single
+    /// hash computation.
+    pub fn hash_and_shard(&self, key: &[u8]) -> (u64, u32) {
+        debug_assert!(
+            self.config.fixed_length.is_none_or(|len| len == key.len()),
+            "key length differs from the config's fixed_length"
+        );
+        let hash = self.hash64(key);
+        let shard = self.reduce(hash);
+        self.record(key.len(), None, shard);
+        (hash, shard)
+    }
+
+    /// Shards `key` ignoring its first `skip` bytes, for keys with a
+    /// low-entropy prefix. The full length is still mixed in (unless the
+    /// config sets `fixed_length`, which makes it redundant), but keys that
+    /// have the same length and are identical after the prefix always
+    /// collide, whatever their prefixes.
+    pub fn shard_skip_prefix(&self, key: &[u8], skip: usize) -> u32 {
+        let rest = &key[skip.min(key.len())..];
+        let hash = if self.config.fixed_length.is_some() {
+            self.hash64(rest)
+        } else {
+            mix::splitmix64(self.hash64(rest) ^ mix::splitmix64(key.len() as u64))
+        };
+        let shard = self.reduce(hash);
+        let algorithm = self.observer.is_some().then(|| self.get_algorithm_for_size(rest.len()));
+        self.record(key.len(), algorithm.as_ref(), shard);
+        shard
+    }
+
+    /// Shards a composite `(a, b)` key. Each part is hashed on its own and
+    /// the two hashes are mixed order-sensitively, so `(a, b)` and `(b, a)`
+    /// land independently and part boundaries matter (`("ab", "c")` differs
+    /// from `("a", "bc")`).
+    pub fn shard_pair(&self, a: &[u8], b: &[u8]) -> u32 {
+        let shard = self.reduce(mix::combine(self.hash64(a), self.hash64(b)));
+        let algorithm = self.observer.is_some().then(|| self.get_algorithm_for_size(a.len().max(b.len())));
+        self.record(a.len() + b.len(), algorithm.as_ref(), shard);
+        shard
+    }
+
+    /// Shards `key` in the key space of `prefix`, e.g. a tenant id, so
+    /// tenants sharing keys get independent distributions. The two are
+    /// hashed separately and mixed, never concatenated; this is
+    /// [`shard_pair`](Self::shard_pair) with the prefix first. Keys whose
+    /// own 64-bit hashes collide still collide under every prefix.
+    pub fn shard_with_prefix(&self, prefix: &[u8], key: &[u8]) -> u32 {
+        self.shard_pair(prefix, key)
+    }
+
+    /// Maps a hash that a legacy system already reduced to 32 bits into the
+    /// shard space with the configured mapping, for gradual migration. Only
+    /// 32 bits of entropy go in, so with large shard counts the modulo bias
+    /// toward low shard ids grows (it is about `shard_count / 2^32`). Under
+    /// [`ReductionMode::Multiply`] the hash is placed in the upper 32 bits,
+    /// which are the ones that mapping reads.
+    pub fn shard_of_u32(&self, hash32: u32) -> u32 {
+        let shard = match self.config.reduction {
+            ReductionMode::Modulo => self.reduce(hash32 as u64),
+            ReductionMode::Multiply => self.reduce((hash32 as u64) << 32),
+        };
+        // No key was hashed, so there is nothing to tell an observer.
+        if let Some(metrics) = &self.metrics {
+            metrics.record(shard);
+        }
+        shard
+    }
+
+    /// Shards a key that `f` produces piecewise: `f` is handed a sink and
+    /// feeds it the key's bytes in any number of chunks. The result equals
+    /// `shard` of the chunks concatenated. The chunks go through a
+    /// [`ShardHasher`], so they are hashed as they arrive when every key
+    /// size resolves to FNV-1a or XXH3 (default secret) and there are no key
+    /// hooks; otherwise they are gathered first, since the algorithm depends
+    /// on the total length.
+    pub fn shard_with_key_fn(&self, f: impl FnOnce(&mut dyn FnMut(&[u8]))) -> u32 {
+        let mut hasher = self.hasher();
+        f(&mut |chunk: &[u8]| hasher.update(chunk));
+        hasher.finish()
+    }
+
+    /// Shards the logical concatenation of `bufs` (no framing between them),
+    /// equal to `shard` of the contiguous bytes. The total length is known
+    /// up front, so when the resolved algorithm is FNV-1a, or XXH3 without a
+    /// custom secret, the buffers are hashed in place with no copy. Other
+    /// algorithms, and instances with key hooks, gather them first.
+    pub fn shard_iovecs(&self, bufs: &[&[u8]]) -> u32 {
+        // A single shard is the answer for every key; skip the gather too.
+        if self.shard_count == 1 {
+            self.record(bufs.iter().map(|buf| buf.len()).sum(), None, 0);
+            return 0;
+        }
+        let Some(hash) = self.hash_iovecs_in_place(bufs) else {
+            return self.shard(&bufs.concat());
+        };
+        let shard = self.reduce(hash);
+        self.record(bufs.iter().map(|buf| buf.len()).sum(), None, shard);
+        shard
+    }
+
+    // Streams `bufs` through the resolved algorithm when it can do so and
+    // match `shard` exactly; `None` means the caller must gather.
+    fn hash_iovecs_in_place(&self, bufs: &[&[u8]]) -> Option<u64> {
+        if self.has_key_hooks() {
+            return None;
+        }
+        let total = bufs.iter().map(|buf| buf.len()).sum();
+        debug_assert!(
+            self.config.fixed_length.is_none_or(|len| len == total),
+            "key length differs from the config's fixed_length"
+        );
+        match self.get_algorithm_for_size(total) {
+            #[cfg(feature = "fnv")]
+            ShardAlgorithm::Fnv1a => {
+                let basis = fnv1a_basis(self.config.seed);
+                Some(bufs.iter().fold(basis, |hash, buf| fnv1a_64_update(hash, buf)))
+            }
+            #[cfg(feature = "xxh3")]
+            ShardAlgorithm::Xxh3 if self.xxh3_secret.is_none() => {
+                let mut state = xxhash_rust::xxh3::Xxh3::with_seed(self.config.seed);
+                for buf in bufs {
+                    state.update(buf);
                 }
-                hash % self.shard_count
-            } else {
-                self.shard_with_xxh3(key)
+                Some(state.digest())
             }
+            _ => None,
+        }
+    }
+
+    /// Shards `key` with `algorithm` regardless of the configured tiers (and
+    /// of a [`FastShard::with_hasher`] closure), e.g. to match an external
+    /// system that placed keys with FNV-1a. Everything else about the
+    /// instance applies as in [`FastShard::shard`]: normalization, key
+    /// transform, padding, seed, finalizer and reduction. An algorithm the
+    /// host cannot run falls back as it would in a tier. Equal to `shard`
+    /// on an instance whose config lists only `algorithm`.
+    pub fn shard_with(&self, key: &[u8], algorithm: ShardAlgorithm) -> u32 {
+        let algorithm = self.get_available_algorithm(&[algorithm]);
+        let shard = if self.shard_count == 1 {
+            0
+        } else {
+            self.reduce(self.hash_transformed_with(&self.transform_key(key), Some(&algorithm)))
+        };
+        self.record(key.len(), Some(&algorithm), shard);
+        shard
+    }
+
+    /// [`FastShard::shard`] for anything that views as bytes, so strings,
+    /// vectors and arrays need no conversion: `shard_bytes("user:42")`
+    /// equals `shard(b"user:42")`.
+    pub fn shard_bytes<K: AsRef<[u8]>>(&self, key: K) -> u32 {
+        self.shard(key.as_ref())
+    }
+
+    /// Shards an integer key. Equivalent to `shard(&key.to_le_bytes())`, so
+    /// placement does not depend on the host's byte order.
+    pub fn shard_u64(&self, key: u64) -> u32 {
+        self.shard(&key.to_le_bytes())
+    }
+
+    /// Shards a 128-bit key such as an IPv6 address. Equivalent to
+    /// `shard(&addr.to_le_bytes())`; the 16-byte key fits a single AES-NI
+    /// block when a tier selects that kernel.
+    pub fn shard_u128(&self, addr: u128) -> u32 {
+        self.shard(&addr.to_le_bytes())
+    }
+
+    /// The full hash of `key` before it is mapped onto the shard space, for
+    /// bucketing at other granularities. It reflects the config's
+    /// normalization, key transform, `min_key_len` padding and custom
+    /// hasher, but not its `finalizer` or `salt_with_shard_count`, which
+    /// belong to the mapping. XXH3, FNV-1a and the std hasher fill all 64
+    /// bits natively; the SIMD kernels produce 32-bit hashes that are widened
+    /// through the fmix64 avalanche, so the high bits are mixed rather than
+    /// zero. `shard(key)` is this hash mapped onto a shard.
+    pub fn hash64(&self, key: &[u8]) -> u64 {
+        self.hash_transformed(&self.transform_key(key))
+    }
+
+    fn transform_key<'a>(&self, key: &'a [u8]) -> Cow<'a, [u8]> {
+        let normalized = self.config.normalization.apply(key);
+        match (&self.key_transform, normalized) {
+            (None, normalized) => normalized,
+            (Some(transform), Cow::Borrowed(key)) => transform(key),
+            (Some(transform), Cow::Owned(key)) => Cow::Owned(transform(&key).into_owned()),
         }
     }
 
-    #[cfg(not(target_feature = "avx512f"))]
-    fn shard_with_avx512(&self, key: &[u8]) -> u32 {
-        self.shard_with_xxh3(key)
+    // Counts a placement in the metrics and reports it to the observer,
+    // whichever are enabled. `forced` is the algorithm that served the key,
+    // when the caller already has it or it is not what `key_len` resolves
+    // to (`shard_with`, a skipped prefix, a pair).
+    #[inline]
+    fn record(&self, key_len: usize, forced: Option<&ShardAlgorithm>, shard: u32) {
+        if let Some(metrics) = &self.metrics {
+            metrics.record(shard);
+        }
+        if let Some(observer) = &self.observer {
+            let algorithm = match forced {
+                Some(algorithm) => algorithm.clone(),
+                None => self.get_algorithm_for_size(key_len),
+            };
+            observer(ShardEvent {
+                key_len,
+                algorithm,
+                shard,
+            });
+        }
     }
 
-    #[cfg(target_feature = "avx2")]
-    fn shard_with_avx2(&self, key: &[u8]) -> u32 {
-        unsafe {
-            if is_x86_feature_detected!("avx2") {
-                let mut hash = 0u32;
-                for chunk in key.chunks(32) {
-                    let vec = if chunk.len() == 32 {
-                        _mm256_loadu_si256(chunk.as_ptr() as *const _)
-                    } else {
-                        let mut padded = [0u8; 32];
-                        padded[..chunk.len()].copy_from_slice(chunk);
-                        _mm256_loadu_si256(padded.as_ptr() as *const _)
-                    };
-                    
-                    let reduced = _mm256_extract_epi32::<0>(vec) as u32;
-                    hash = hash.wrapping_add(reduced);
-                }
-                hash % self.shard_count
-            } else {
-                self.shard_with_xxh3(key)
+    // Whether keys must go through `hash64` one by one rather than straight
+    // to a resolved kernel.
+    fn has_key_hooks(&self) -> bool {
+        self.custom_hasher.is_some()
+            || self.key_transform.is_some()
+            || !self.config.normalization.is_identity()
+            || self.config.min_key_len.is_some()
+    }
+
+    // `hash64` for a key that has already been through `transform_key`.
+    fn hash_transformed(&self, key: &[u8]) -> u64 {
+        self.hash_transformed_with(key, None)
+    }
+
+    // `hash_transformed`, hashing with `forced` (already resolved to a
+    // usable algorithm) instead of the tier's algorithm or custom hasher.
+    fn hash_transformed_with(&self, key: &[u8], forced: Option<&ShardAlgorithm>) -> u64 {
+        match self.config.min_key_len {
+            Some(min) if key.len() < min => {
+                let mut padded = key.to_vec();
+                padded.resize(min, 0);
+                mix::splitmix64(self.hash_unpadded(&padded, forced) ^ mix::splitmix64(key.len() as u64))
             }
+            _ => self.hash_unpadded(key, forced),
+        }
+    }
+
+    fn hash_unpadded(&self, key: &[u8], forced: Option<&ShardAlgorithm>) -> u64 {
+        if let Some(algorithm) = forced {
+            return self.hash_with_algorithm(key, algorithm.clone());
+        }
+        if let Some(hasher) = &self.custom_hasher {
+            return hasher(key);
         }
+        self.hash_with_algorithm(key, self.get_algorithm_for_size(key.len()))
     }
 
-    #[cfg(not(target_feature = "avx2"))]
-    fn shard_with_avx2(&self, key: &[u8]) -> u32 {
-        self.shard_with_xxh3(key)
+    fn reduce(&self, hash: u64) -> u32 {
+        let hash = self.prepare(hash, self.shard_count as u64);
+        #[cfg(feature = "std")]
+        if let Some(weights) = &self.weights {
+            return rendezvous::select_weighted(hash, weights);
+        }
+        self.reducer.reduce(hash)
     }
 
-    #[cfg(target_feature = "aes")]
-    fn shard_with_aesni(&self, key: &[u8]) -> u32 {
-        unsafe {
-            if is_x86_feature_detected!("aes") {
-                let mut hash = _mm_set1_epi32(0);
-                for chunk in key.chunks(16) {
-                    let data = if chunk.len() == 16 {
-                        _mm_loadu_si128(chunk.as_ptr() as *const _)
-                    } else {
-                        let mut padded = [0u8; 16];
-                        padded[..chunk.len()].copy_from_slice(chunk);
-                        _mm_loadu_si128(padded.as_ptr() as *const _)
-                    };
-                    
-                    hash = _mm_aesenc_si128(hash, data);
-                }
-                let result = _mm_extract_epi32::<0>(hash) as u32;
-                result % self.shard_count
-            } else {
-                self.shard_with_xxh3(key)
+    // The config's finalizer and shard-count salt, applied before any
+    // mapping onto `0..count`.
+    fn prepare(&self, hash: u64, count: u64) -> u64 {
+        let hash = self.config.finalizer.apply(hash);
+        if self.config.salt_with_shard_count {
+            mix::splitmix64(hash ^ mix::splitmix64(count))
+        } else {
+            hash
+        }
+    }
+
+    // Whether `with_adaptive_kernels` is on; it needs `std` to time kernels.
+    fn has_adaptive_kernels(&self) -> bool {
+        #[cfg(feature = "std")]
+        return self.adaptive.is_some();
+        #[cfg(not(feature = "std"))]
+        false
+    }
+
+    fn hash_with_algorithm(&self, key: &[u8], algorithm: ShardAlgorithm) -> u64 {
+        #[cfg(feature = "std")]
+        if let Some(adaptive) = &self.adaptive {
+            if let Some(hash) = adaptive.hash(key, self.config.seed, self.folded_len(key), &algorithm, self.cpu) {
+                return mix::finalize(hash);
             }
         }
+        match algorithm {
+            ShardAlgorithm::Avx512 => self.hash_with_avx512(key),
+            ShardAlgorithm::Avx2 => self.hash_with_avx2(key),
+            ShardAlgorithm::AesNi => self.hash_with_aesni(key),
+            ShardAlgorithm::Neon => self.hash_with_neon(key),
+            ShardAlgorithm::Simd128 => self.hash_with_simd128(key),
+            ShardAlgorithm::Crc32c => self.hash_with_crc32c(key),
+            ShardAlgorithm::Fnv1a => self.hash_with_fnv1a(key),
+            ShardAlgorithm::Xxh3 => self.hash_with_xxh3(key),
+            ShardAlgorithm::Wyhash => self.hash_with_wyhash(key),
+            ShardAlgorithm::SipHash13 => self.hash_with_siphash(key),
+            ShardAlgorithm::StdDefault => self.hash_with_std_default(key),
+        }
     }
 
-    #[cfg(not(target_feature = "aes"))]
-    fn shard_with_aesni(&self, key: &[u8]) -> u32 {
-        self.shard_with_xxh3(key)
+    // The length the SIMD kernels fold into their final avalanche: none when
+    // the config fixes the key length, so they hash the bytes alone.
+    fn folded_len(&self, key: &[u8]) -> usize {
+        if self.config.fixed_length.is_some() {
+            0
+        } else {
+            key.len()
+        }
     }
 
-    fn shard_with_fnv1a(&self, key: &[u8]) -> u32 {
-        let mut hasher = fnv::FnvHasher::default();
-        use std::hash::Hasher;
-        hasher.write(key);
-        (hasher.finish() % self.shard_count as u64) as u32
+    // The SIMD kernels produce 32-bit hashes, widened by `mix::finalize`.
+    // Their XXH3 fallbacks are left as is.
+    #[cfg(all(feature = "avx512", target_arch = "x86_64"))]
+    fn hash_with_avx512(&self, key: &[u8]) -> u64 {
+        if self.cpu.avx512f {
+            let len = self.folded_len(key);
+            mix::finalize(unsafe { simd::avx512_hash_in(key, self.config.seed, len, &mut [0u8; 64]) } as u64)
+        } else {
+            self.hash_with_xxh3(key)
+        }
+    }
+
+    #[cfg(not(all(feature = "avx512", target_arch = "x86_64")))]
+    fn hash_with_avx512(&self, key: &[u8]) -> u64 {
+        self.hash_with_xxh3(key)
+    }
+
+    #[cfg(all(feature = "avx2", target_arch = "x86_64"))]
+    fn hash_with_avx2(&self, key: &[u8]) -> u64 {
+        if self.cpu.avx2 {
+            let len = self.folded_len(key);
+            mix::finalize(unsafe { simd::avx2_hash_in(key, self.config.seed, len, &mut [0u8; 64]) } as u64)
+        } else {
+            self.hash_with_xxh3(key)
+        }
+    }
+
+    #[cfg(not(all(feature = "avx2", target_arch = "x86_64")))]
+    fn hash_with_avx2(&self, key: &[u8]) -> u64 {
+        self.hash_with_xxh3(key)
+    }
+
+    #[cfg(all(feature = "aesni", target_arch = "x86_64"))]
+    fn hash_with_aesni(&self, key: &[u8]) -> u64 {
+        if self.cpu.aes {
+            let len = self.folded_len(key);
+            mix::finalize(unsafe { simd::aesni_hash_in(key, self.config.seed, len, &mut [0u8; 64]) } as u64)
+        } else {
+            self.hash_with_xxh3(key)
+        }
+    }
+
+    #[cfg(not(all(feature = "aesni", target_arch = "x86_64")))]
+    fn hash_with_aesni(&self, key: &[u8]) -> u64 {
+        self.hash_with_xxh3(key)
+    }
+
+    #[cfg(all(feature = "neon", target_arch = "aarch64"))]
+    fn hash_with_neon(&self, key: &[u8]) -> u64 {
+        if self.cpu.neon {
+            let len = self.folded_len(key);
+            mix::finalize(unsafe { neon::neon_hash_in(key, self.config.seed, len, &mut [0u8; 64]) } as u64)
+        } else {
+            self.hash_with_xxh3(key)
+        }
+    }
+
+    #[cfg(not(all(feature = "neon", target_arch = "aarch64")))]
+    fn hash_with_neon(&self, key: &[u8]) -> u64 {
+        self.hash_with_xxh3(key)
+    }
+
+    // Only compiled where SIMD128 is statically available, so no check.
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128", feature = "simd128"))]
+    fn hash_with_simd128(&self, key: &[u8]) -> u64 {
+        let len = self.folded_len(key);
+        mix::finalize(wasm::simd128_hash_in(key, self.config.seed, len, &mut [0u8; 64]) as u64)
+    }
+
+    #[cfg(not(all(target_arch = "wasm32", target_feature = "simd128", feature = "simd128")))]
+    fn hash_with_simd128(&self, key: &[u8]) -> u64 {
+        self.hash_with_xxh3(key)
+    }
+
+    #[cfg(all(feature = "crc32c", target_arch = "x86_64"))]
+    fn hash_with_crc32c(&self, key: &[u8]) -> u64 {
+        if self.cpu.sse42 {
+            mix::finalize(unsafe { simd::crc32c_hash_seeded(key, self.config.seed) } as u64)
+        } else {
+            self.hash_with_xxh3(key)
+        }
     }
 
-    fn shard_with_xxh3(&self, key: &[u8]) -> u32 {
-        use xxhash_rust::xxh3::xxh3_64;
-        (xxh3_64(key) % self.shard_count as u64) as u32
+    #[cfg(not(all(feature = "crc32c", target_arch = "x86_64")))]
+    fn hash_with_crc32c(&self, key: &[u8]) -> u64 {
+        self.hash_with_xxh3(key)
+    }
+
+    #[cfg(feature = "fnv")]
+    fn hash_with_fnv1a(&self, key: &[u8]) -> u64 {
+        fnv1a_64(key, self.config.seed)
+    }
+
+    #[cfg(not(feature = "fnv"))]
+    fn hash_with_fnv1a(&self, key: &[u8]) -> u64 {
+        self.hash_with_xxh3(key)
+    }
+
+    #[cfg(feature = "wyhash")]
+    fn hash_with_wyhash(&self, key: &[u8]) -> u64 {
+        wyhash::wyhash(key, self.config.seed)
+    }
+
+    #[cfg(not(feature = "wyhash"))]
+    fn hash_with_wyhash(&self, key: &[u8]) -> u64 {
+        self.hash_with_xxh3(key)
+    }
+
+    #[cfg(feature = "siphash")]
+    fn hash_with_siphash(&self, key: &[u8]) -> u64 {
+        let (k0, k1) = siphash::key_from_seed(self.config.seed);
+        siphash::siphash13(k0, k1, key)
+    }
+
+    #[cfg(not(feature = "siphash"))]
+    fn hash_with_siphash(&self, key: &[u8]) -> u64 {
+        self.hash_with_xxh3(key)
+    }
+
+    #[cfg(feature = "std")]
+    fn hash_with_std_default(&self, key: &[u8]) -> u64 {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+        let mut hasher = DefaultHasher::new();
+        if self.config.seed != 0 {
+            hasher.write_u64(self.config.seed);
+        }
+        key.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn hash_with_std_default(&self, key: &[u8]) -> u64 {
+        self.hash_with_xxh3(key)
+    }
+
+    #[cfg(feature = "xxh3")]
+    fn hash_with_xxh3(&self, key: &[u8]) -> u64 {
+        use xxhash_rust::xxh3::{xxh3_64_with_secret, xxh3_64_with_seed};
+        match &self.xxh3_secret {
+            Some(secret) => xxh3_64_with_secret(key, secret),
+            None => xxh3_64_with_seed(key, self.config.seed),
+        }
+    }
+
+    #[cfg(not(feature = "xxh3"))]
+    fn hash_with_xxh3(&self, key: &[u8]) -> u64 {
+        self.hash_with_fnv1a(key)
     }
 }
 
+/// Checked counterpart of [`FastShard::with_config`].
+impl TryFrom<(u32, ShardConfig)> for FastShard {
+    type Error = ShardError;
+
+    fn try_from((shard_count, config): (u32, ShardConfig)) -> Result<Self, Self::Error> {
+        if shard_count == 0 {
+            return Err(ShardError::ZeroShardCount);
+        }
+        config.validate()?;
+        Ok(Self::with_config(shard_count, config))
+    }
+}
 
 // Add test module
 #[cfg(test)]
@@ -238,6 +1361,7 @@ mod tests {
                 },
             ],
             default_algorithms: vec![ShardAlgorithm::Xxh3],
+            ..ShardConfig::default()
         };
 
         let shard = FastShard::with_config(16, config);
@@ -250,12 +1374,1733 @@ mod tests {
         let _ = shard.shard(&large_key);
     }
 
-    #[test]
-    fn test_default_config() {
-        let shard = FastShard::new(16);
-        
-        // Test various key sizes
-        let keys = vec![
+    // Lengths straddle every 16/32/64-byte chunk boundary.
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    fn cross_check_keys() -> Vec<Vec<u8>> {
+        (0..=200usize)
+            .map(|len| (0..len).map(|i| (i as u8).wrapping_mul(31).wrapping_add(len as u8)).collect())
+            .collect()
+    }
+
+    // Where `shard` places a raw SIMD kernel hash among `count` shards.
+    fn simd_shard(hash: u32, count: u32) -> u32 {
+        (mix::finalize(hash as u64) % count as u64) as u32
+    }
+
+    // Compares the SIMD path `shard` takes against the scalar model. A no-op
+    // unless the feature is both compiled in and present at runtime.
+    #[cfg(target_arch = "x86_64")]
+    fn cross_check(algo: ShardAlgorithm, available: bool, reference: fn(&[u8]) -> u32) {
+        if !available {
+            return;
+        }
+        let shard = FastShard::with_config(1021, ShardConfig::single(algo.clone()));
+        for key in cross_check_keys() {
+            assert_eq!(shard.shard(&key), simd_shard(reference(&key), 1021), "{:?} len {}", algo, key.len());
+        }
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn test_simd_shard_matches_scalar_reference() {
+        cross_check(
+            ShardAlgorithm::Avx512,
+            cpu::CpuFeatures::detect().avx512f,
+            reference::avx512_hash,
+        );
+        cross_check(
+            ShardAlgorithm::Avx2,
+            cpu::CpuFeatures::detect().avx2,
+            reference::avx2_hash,
+        );
+        cross_check(
+            ShardAlgorithm::AesNi,
+            cpu::CpuFeatures::detect().aes,
+            reference::aesni_hash,
+        );
+        cross_check(
+            ShardAlgorithm::Crc32c,
+            cpu::CpuFeatures::detect().sse42,
+            reference::crc32c_hash,
+        );
+    }
+
+    // The SIMD kernels are selected at runtime, so a build without
+    // `-C target-feature` flags still takes them on a capable host.
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn test_which_algorithm_detects_simd_at_runtime() {
+        let expected = if cfg!(feature = "avx512") && is_x86_feature_detected!("avx512f") {
+            ShardAlgorithm::Avx512
+        } else if cfg!(feature = "avx2") && is_x86_feature_detected!("avx2") {
+            ShardAlgorithm::Avx2
+        } else if cfg!(feature = "aesni") && is_x86_feature_detected!("aes") {
+            ShardAlgorithm::AesNi
+        } else if cfg!(feature = "fnv") {
+            ShardAlgorithm::Fnv1a
+        } else {
+            ShardAlgorithm::Xxh3
+        };
+        let shard = FastShard::new(1024);
+        assert_eq!(shard.which_algorithm(8), expected);
+
+        let avx2 = FastShard::with_config(1024, ShardConfig::single(ShardAlgorithm::Avx2));
+        if cfg!(feature = "avx2") && is_x86_feature_detected!("avx2") {
+            assert_eq!(avx2.which_algorithm(100), ShardAlgorithm::Avx2);
+        } else {
+            assert_eq!(avx2.which_algorithm(100), fallback_algorithm());
+        }
+    }
+
+    #[test]
+    fn test_avx2_kernel_uses_every_lane() {
+        // The keys differ only in bytes 4..8 and 40..44, lanes the kernel
+        // once discarded by keeping just lane 0 of each chunk.
+        let keys: Vec<[u8; 64]> = (0..10_000u32)
+            .map(|i| {
+                let mut key = [0x5a_u8; 64];
+                key[4..8].copy_from_slice(&i.to_le_bytes());
+                key[40..44].copy_from_slice(&(i >> 8).to_le_bytes());
+                key
+            })
+            .collect();
+        let mut counts = [0u64; 256];
+        for key in &keys {
+            counts[(reference::avx2_hash(key) % 256) as usize] += 1;
+        }
+        assert_uniform(&counts);
+
+        if cpu::CpuFeatures::detect().avx2 {
+            let shard = FastShard::with_config(256, ShardConfig::single(ShardAlgorithm::Avx2));
+            for key in keys.iter().step_by(97) {
+                assert_eq!(shard.shard(key), simd_shard(reference::avx2_hash(key), 256));
+            }
+        }
+    }
+
+    // Pearson's chi-square statistic of `counts` against a uniform spread.
+    fn chi_square(counts: &[u64]) -> f64 {
+        let expected = counts.iter().sum::<u64>() as f64 / counts.len() as f64;
+        counts.iter().map(|&c| (c as f64 - expected).powi(2) / expected).sum()
+    }
+
+    // Fails if `counts` is less uniform than chance allows at p = 0.001. The
+    // critical value is the Wilson-Hilferty approximation, e.g. ~330 for 256
+    // buckets.
+    fn assert_uniform(counts: &[u64]) {
+        let dof = (counts.len() - 1) as f64;
+        // Upper 0.001 quantile of the standard normal.
+        let z = 3.090;
+        let critical = dof * (1.0 - 2.0 / (9.0 * dof) + z * (2.0 / (9.0 * dof)).sqrt()).powi(3);
+        let statistic = chi_square(counts);
+        assert!(statistic < critical, "chi-square {} above {} for {} buckets", statistic, critical, counts.len());
+    }
+
+    // The weighted 32-byte lane sum the AVX2 kernel once returned as is:
+    // linear, so its low bits see only the low bits of each lane.
+    fn plain_lane_sum(key: &[u8]) -> u32 {
+        key.chunks(4).zip(reference::AVX2_LANE_WEIGHTS.iter().cycle()).fold(0u32, |acc, (lane, weight)| {
+            let mut bytes = [0u8; 4];
+            bytes[..lane.len()].copy_from_slice(lane);
+            acc.wrapping_add(u32::from_le_bytes(bytes).wrapping_mul(*weight))
+        })
+    }
+
+    #[test]
+    fn test_simd_finalizer_fixes_power_of_two_counts() {
+        // 32-byte keys whose lanes differ only above their low byte. A plain
+        // lane sum's low 8 bits, all a power-of-two modulo keeps, see only
+        // the low bytes, so the raw sum puts every key on one of 256 shards;
+        // the finalizer every SIMD kernel's hash goes through spreads them.
+        let keys: Vec<[u8; 32]> = (0..25_600u64)
+            .map(|i| {
+                let mut key = [0x11u8; 32];
+                for (lane, chunk) in key.chunks_mut(4).enumerate() {
+                    let high = (mix::splitmix64(i * 8 + lane as u64) as u32) << 8;
+                    chunk.copy_from_slice(&(high | 0x11).to_le_bytes());
+                }
+                key
+            })
+            .collect();
+        let counts = |shard_of: &dyn Fn(&[u8]) -> u32| {
+            let mut counts = [0u64; 256];
+            for key in &keys {
+                counts[shard_of(key) as usize] += 1;
+            }
+            counts
+        };
+
+        let before = chi_square(&counts(&|key| plain_lane_sum(key) % 256));
+        assert!(before > 100_000.0, "before: {}", before);
+        assert_uniform(&counts(&|key| simd_shard(plain_lane_sum(key), 256)));
+
+        if cpu::CpuFeatures::detect().avx2 {
+            let shard = FastShard::with_config(256, ShardConfig::single(ShardAlgorithm::Avx2));
+            assert_uniform(&counts(&|key| shard.shard(key)));
+        }
+    }
+
+    #[test]
+    fn test_avx512_kernel_is_order_sensitive() {
+        assert_ne!(reference::avx512_hash(b"abc"), reference::avx512_hash(b"cba"));
+        assert_ne!(reference::avx512_hash(b"a"), reference::avx512_hash(b"a\0"));
+
+        // Swapping any two 4-byte lanes, or two whole 64-byte chunks, moves
+        // the hash; a plain lane sum would not notice.
+        let key: Vec<u8> = (0..128u32).map(|i| (i * 37 + 11) as u8).collect();
+        let base = reference::avx512_hash(&key);
+        for a in 0..16 {
+            for b in a + 1..16 {
+                let mut swapped = key.clone();
+                for i in 0..4 {
+                    swapped.swap(a * 4 + i, b * 4 + i);
+                }
+                assert_ne!(reference::avx512_hash(&swapped), base, "lanes {} and {}", a, b);
+            }
+        }
+        let mut chunks = key[64..].to_vec();
+        chunks.extend_from_slice(&key[..64]);
+        assert_ne!(reference::avx512_hash(&chunks), base);
+
+        if cpu::CpuFeatures::detect().avx512f {
+            let shard = FastShard::with_config(1 << 20, ShardConfig::single(ShardAlgorithm::Avx512));
+            assert_ne!(shard.shard(b"abc"), shard.shard(b"cba"));
+            assert_eq!(shard.shard(&chunks), simd_shard(reference::avx512_hash(&chunks), 1 << 20));
+        }
+    }
+
+    #[test]
+    fn test_avx2_kernel_is_order_and_length_sensitive() {
+        // Swapping two whole 32-byte chunks moves the hash.
+        let key: Vec<u8> = (0..96u32).map(|i| (i * 37 + 11) as u8).collect();
+        let mut swapped = key[32..64].to_vec();
+        swapped.extend_from_slice(&key[..32]);
+        swapped.extend_from_slice(&key[64..]);
+        assert_ne!(reference::avx2_hash(&swapped), reference::avx2_hash(&key));
+
+        // Trailing zeros are not absorbed by the padding, seeded or not.
+        for seed in [0, 7] {
+            let hashes: Vec<u32> = [&b"abc"[..], b"abc\0", b"abc\0\0", &[0; 32], &[0; 33]]
+                .iter()
+                .map(|key| reference::avx2_hash_seeded(key, seed))
+                .collect();
+            for (i, a) in hashes.iter().enumerate() {
+                assert!(hashes[i + 1..].iter().all(|b| b != a), "seed {}: {:x?}", seed, hashes);
+            }
+        }
+
+        if cpu::CpuFeatures::detect().avx2 {
+            let shard = FastShard::with_config(1 << 20, ShardConfig::single(ShardAlgorithm::Avx2));
+            assert_ne!(shard.shard(&swapped), shard.shard(&key));
+            assert_ne!(shard.shard(b"abc"), shard.shard(b"abc\0"));
+        }
+    }
+
+    // Every length from empty through three full 64-byte chunks, so each
+    // kernel sees full chunks and every partial tail of 16/32/64 bytes.
+    #[test]
+    fn test_kernel_padding_handles_every_chunk_boundary() {
+        let key: Vec<u8> = (0..=255u8).collect();
+        for len in 0..=193 {
+            let key = &key[..len];
+            reference::avx512_hash(key);
+            reference::avx2_hash(key);
+            reference::aesni_hash(key);
+            #[cfg(all(target_arch = "x86_64", feature = "avx512"))]
+            if is_x86_feature_detected!("avx512f") {
+                assert_eq!(unsafe { simd::avx512_hash(key) }, reference::avx512_hash(key));
+            }
+            #[cfg(all(target_arch = "x86_64", feature = "avx2"))]
+            if is_x86_feature_detected!("avx2") {
+                assert_eq!(unsafe { simd::avx2_hash(key) }, reference::avx2_hash(key));
+            }
+            #[cfg(all(target_arch = "x86_64", feature = "aesni"))]
+            if is_x86_feature_detected!("aes") {
+                assert_eq!(unsafe { simd::aesni_hash(key) }, reference::aesni_hash(key));
+            }
+        }
+    }
+
+    // An inconsistent feature check would flip single keys between the
+    // kernel and XXH3. Pinning to the scalar model also proves the kernel,
+    // not the fallback, ran.
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn test_simd_shard_is_stable_across_repeats() {
+        type Kernel = (ShardAlgorithm, bool, fn(&[u8]) -> u32);
+        let kernels: [Kernel; 3] = [
+            (
+                ShardAlgorithm::Avx512,
+                cpu::CpuFeatures::detect().avx512f,
+                reference::avx512_hash,
+            ),
+            (
+                ShardAlgorithm::Avx2,
+                cpu::CpuFeatures::detect().avx2,
+                reference::avx2_hash,
+            ),
+            (
+                ShardAlgorithm::AesNi,
+                cpu::CpuFeatures::detect().aes,
+                reference::aesni_hash,
+            ),
+        ];
+        let key = b"repeat-me-for-a-stable-kernel-path-0123456789";
+        for (algo, available, reference) in kernels {
+            if !available {
+                continue;
+            }
+            let shard = FastShard::with_config(1021, ShardConfig::single(algo.clone()));
+            let expected = simd_shard(reference(key), 1021);
+            for _ in 0..1000 {
+                assert_eq!(shard.shard(key), expected, "{:?}", algo);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(all(
+        target_arch = "x86_64",
+        any(feature = "avx512", feature = "avx2", feature = "aesni", feature = "crc32c")
+    ))]
+    fn test_simd_kernels_match_scalar_reference_when_detected() {
+        for key in cross_check_keys() {
+            #[cfg(feature = "avx512")]
+            if is_x86_feature_detected!("avx512f") {
+                assert_eq!(unsafe { simd::avx512_hash(&key) }, reference::avx512_hash(&key));
+            }
+            #[cfg(feature = "avx2")]
+            if is_x86_feature_detected!("avx2") {
+                assert_eq!(unsafe { simd::avx2_hash(&key) }, reference::avx2_hash(&key));
+            }
+            #[cfg(feature = "aesni")]
+            if is_x86_feature_detected!("aes") {
+                assert_eq!(unsafe { simd::aesni_hash(&key) }, reference::aesni_hash(&key));
+            }
+            #[cfg(feature = "crc32c")]
+            if is_x86_feature_detected!("sse4.2") {
+                assert_eq!(unsafe { simd::crc32c_hash(&key) }, reference::crc32c_hash(&key));
+            }
+            for seed in [1, 2, 0xdead_beef_cafe_f00d] {
+                #[cfg(feature = "avx512")]
+                if is_x86_feature_detected!("avx512f") {
+                    let simd = unsafe { simd::avx512_hash_seeded(&key, seed) };
+                    assert_eq!(simd, reference::avx512_hash_seeded(&key, seed));
+                }
+                #[cfg(feature = "avx2")]
+                if is_x86_feature_detected!("avx2") {
+                    let simd = unsafe { simd::avx2_hash_seeded(&key, seed) };
+                    assert_eq!(simd, reference::avx2_hash_seeded(&key, seed));
+                }
+                #[cfg(feature = "aesni")]
+                if is_x86_feature_detected!("aes") {
+                    let simd = unsafe { simd::aesni_hash_seeded(&key, seed) };
+                    assert_eq!(simd, reference::aesni_hash_seeded(&key, seed));
+                }
+                #[cfg(feature = "crc32c")]
+                if is_x86_feature_detected!("sse4.2") {
+                    let simd = unsafe { simd::crc32c_hash_seeded(&key, seed) };
+                    assert_eq!(simd, reference::crc32c_hash_seeded(&key, seed));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_scalar_reference_known_values() {
+        // AESENC example from Intel's AES-NI white paper (values are
+        // written most-significant byte first).
+        let mut state: [u8; 16] = [
+            0x7b, 0x5b, 0x54, 0x65, 0x73, 0x74, 0x56, 0x65,
+            0x63, 0x74, 0x6f, 0x72, 0x5d, 0x53, 0x47, 0x5d,
+        ];
+        let mut round_key: [u8; 16] = [
+            0x48, 0x69, 0x28, 0x53, 0x68, 0x61, 0x79, 0x29,
+            0x5b, 0x47, 0x75, 0x65, 0x72, 0x6f, 0x6e, 0x5d,
+        ];
+        let mut expected: [u8; 16] = [
+            0xa8, 0x31, 0x1c, 0x2f, 0x9f, 0xdb, 0xa3, 0xc5,
+            0x8b, 0x10, 0x4b, 0x58, 0xde, 0xd7, 0xe5, 0x95,
+        ];
+        state.reverse();
+        round_key.reverse();
+        expected.reverse();
+        assert_eq!(reference::aesenc(state, round_key), expected);
+
+        let mut key = [0u8; 64];
+        key[0] = 1;
+        key[4] = 2;
+        key[32] = 3;
+        // Two AVX2 chunks: lanes 0 and 1 of the first (1, 2), lane 0 of the
+        // second (3), each chunk folded in with its index.
+        let w = reference::AVX2_LANE_WEIGHTS;
+        let first = reference::chunk_fold(0, 0, w[0].wrapping_add(w[1].wrapping_mul(2)));
+        let expected = reference::chunk_finish(reference::chunk_fold(first, 1, w[0].wrapping_mul(3)), 64);
+        assert_eq!(reference::avx2_hash(&key), expected);
+        assert_eq!(reference::avx2_hash(&key), 0x1ecf_6b74);
+        assert_eq!(reference::avx512_hash(&key), 0xe37e_95d6);
+        assert_eq!(reference::avx512_hash(&[]), 0);
+
+        // The CRC-32C check value from the Castagnoli catalogue.
+        assert_eq!(reference::crc32c_hash(b"123456789"), 0xe306_9283);
+    }
+
+    // CRC is linear, so a one-byte change moves the hash by the CRC of the
+    // change alone, whatever the rest of the key. For 16-byte keys no such
+    // change clears the low 12 bits, so every one lands on another of 4096
+    // shards; checked exhaustively on the model and, where SSE4.2 runs the
+    // kernel, through `shard`.
+    #[test]
+    fn test_crc32c_separates_one_byte_changes() {
+        let shard = FastShard::with_config(4096, ShardConfig::single(ShardAlgorithm::Crc32c));
+        let hardware = cpu::CpuFeatures::detect().sse42;
+        let base = *b"tenant-0042:0007";
+        for position in 0..base.len() {
+            for byte in 0..=255u8 {
+                if byte == base[position] {
+                    continue;
+                }
+                let mut key = base;
+                key[position] = byte;
+                assert_ne!(
+                    reference::crc32c_hash(&key) % 4096,
+                    reference::crc32c_hash(&base) % 4096,
+                    "byte {} at {}",
+                    byte,
+                    position
+                );
+                if hardware {
+                    assert_eq!(shard.shard(&key), simd_shard(reference::crc32c_hash(&key), 4096));
+                    // The finalizer is a bijection, so the widened hashes
+                    // stay apart even though their low bits may now meet.
+                    assert_ne!(shard.hash64(&key), shard.hash64(&base));
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "xxh3")]
+    fn test_xxh3_secret() {
+        let secret_a: Vec<u8> = (0..XXH3_SECRET_SIZE_MIN as u32).map(|i| (i * 7 + 1) as u8).collect();
+        let secret_b: Vec<u8> = (0..XXH3_SECRET_SIZE_MIN as u32).map(|i| (i * 13 + 5) as u8).collect();
+        let a = FastShard::with_xxh3_secret(64, secret_a).unwrap();
+        let b = FastShard::with_xxh3_secret(64, secret_b).unwrap();
+
+        let agree = (0..1000u32)
+            .filter(|i| {
+                let key = format!("key-{}", i);
+                a.shard(key.as_bytes()) == b.shard(key.as_bytes())
+            })
+            .count();
+        // Independent placements agree on roughly 1/64 of keys.
+        assert!(agree < 60, "secrets agreed on {} of 1000 keys", agree);
+
+        assert_eq!(
+            FastShard::with_xxh3_secret(64, vec![0u8; XXH3_SECRET_SIZE_MIN - 1]).unwrap_err(),
+            ShardError::SecretTooShort {
+                len: XXH3_SECRET_SIZE_MIN - 1,
+                min: XXH3_SECRET_SIZE_MIN,
+            }
+        );
+    }
+
+    #[test]
+    fn test_self_check() {
+        assert_eq!(FastShard::new(16).self_check(), Ok(()));
+        assert_eq!(FastShard::new(1024).self_check(), Ok(()));
+
+        let broken = FastShard::with_hasher(16, |_| 0);
+        assert_eq!(broken.self_check(), Err(ShardError::DegenerateDistribution));
+    }
+
+    #[test]
+    fn test_validate_rejects_disabled_algorithms() {
+        assert_eq!(ShardConfig::default().validate(), Ok(()));
+
+        let all = [
+            ShardAlgorithm::Avx512,
+            ShardAlgorithm::Avx2,
+            ShardAlgorithm::AesNi,
+            ShardAlgorithm::Fnv1a,
+            ShardAlgorithm::Xxh3,
+        ];
+        for algo in all {
+            let result = ShardConfig::single(algo.clone()).validate();
+            if algo.is_enabled() {
+                assert_eq!(result, Ok(()));
+            } else {
+                assert_eq!(result, Err(ConfigError::AlgorithmDisabled(algo)));
+            }
+        }
+    }
+
+    // Run with `cargo test --no-default-features --features std,xxh3`.
+    #[test]
+    #[cfg(all(
+        feature = "xxh3",
+        not(any(feature = "avx512", feature = "avx2", feature = "aesni", feature = "fnv"))
+    ))]
+    fn test_xxh3_only_build() {
+        let config = ShardConfig::default();
+        for tier in &config.tiers {
+            assert_eq!(tier.algorithms, vec![ShardAlgorithm::Xxh3]);
+        }
+        assert_eq!(
+            ShardConfig::single(ShardAlgorithm::Fnv1a).validate(),
+            Err(ConfigError::AlgorithmDisabled(ShardAlgorithm::Fnv1a))
+        );
+
+        let shard = FastShard::new(64);
+        let key = vec![7u8; 40];
+        assert_eq!(
+            shard.shard(&key),
+            (xxhash_rust::xxh3::xxh3_64(&key) % 64) as u32
+        );
+    }
+
+    // Every path reads key bytes in a fixed (little-endian) order, so these
+    // values must hold on any host. A big-endian regression would show up
+    // as a mismatch here.
+    #[test]
+    fn test_cross_endian_pinned_outputs() {
+        // An integer key as `shard_u64` encodes it on every host; the pinned
+        // outputs below hold only if that encoding is byte-order independent.
+        let int_key = 0x0102_0304_0506_0708u64.to_le_bytes();
+
+        let keys: [&[u8]; 5] = [b"", b"a", b"fast-shard", &int_key, &(0..64u8).collect::<Vec<_>>()];
+        let xxh3: [u64; 5] = [
+            0x2d06_8005_38d3_94c2,
+            0xe6c6_32b6_1e96_4e1f,
+            0xd071_50cf_0add_5163,
+            0x908f_af19_5058_ca9e,
+            0x6187_eb90_89b0_ed55,
+        ];
+        let fnv1a: [u64; 5] = [
+            0xcbf2_9ce4_8422_2325,
+            0xaf63_dc4c_8601_ec8c,
+            0xd49d_8d7f_7543_cf5a,
+            0x0c6d_4496_e178_59d5,
+            0x8368_214f_7799_5ee5,
+        ];
+        // Raw SIMD outputs (AVX-512, AVX2, AES-NI) via the scalar models,
+        // which the cross-check tests tie to the intrinsics.
+        let simd: [(u32, u32, u32); 5] = [
+            (0x0000_0000, 0x0000_0000, 0x3105_853e),
+            (0xc420_8856, 0x58ae_0ea4, 0x35ad_72ce),
+            (0xd37a_c7bd, 0x3946_b135, 0x4b7c_8174),
+            (0x944a_6523, 0x3312_da6d, 0x4d2f_99c5),
+            (0xd502_1ae5, 0xb9af_739c, 0x8661_54d0),
+        ];
+
+        let count = u32::MAX;
+        let xxh3_shard = FastShard::with_config(count, ShardConfig::single(ShardAlgorithm::Xxh3));
+        let fnv_shard = FastShard::with_config(count, ShardConfig::single(ShardAlgorithm::Fnv1a));
+        for (i, key) in keys.iter().enumerate() {
+            if cfg!(feature = "xxh3") {
+                assert_eq!(xxh3_shard.shard(key), (xxh3[i] % count as u64) as u32);
+            }
+            if cfg!(feature = "fnv") {
+                assert_eq!(fnv_shard.shard(key), (fnv1a[i] % count as u64) as u32);
+            }
+            assert_eq!(reference::avx512_hash(key), simd[i].0);
+            assert_eq!(reference::avx2_hash(key), simd[i].1);
+            assert_eq!(reference::aesni_hash(key), simd[i].2);
+        }
+        if cfg!(feature = "xxh3") {
+            assert_eq!(xxh3_shard.shard_u64(0x0102_0304_0506_0708), (xxh3[3] % count as u64) as u32);
+        }
+    }
+
+    #[test]
+    fn test_shard16_and_shard64_match_shard() {
+        let keys: Vec<Vec<u8>> = (0..500u32).map(|i| i.to_le_bytes().repeat(1 + i as usize % 9)).collect();
+        for reduction in [ReductionMode::Modulo, ReductionMode::Multiply] {
+            for salt in [false, true] {
+                let config = ShardConfig {
+                    reduction,
+                    salt_with_shard_count: salt,
+                    ..ShardConfig::default()
+                };
+                for count in [1, 7, 1024, 65_536] {
+                    let shard = FastShard::with_config(count, config.clone());
+                    for key in &keys {
+                        assert_eq!(shard.shard16(key), shard.shard(key) as u16);
+                        assert_eq!(shard.shard64(key, count as u64), shard.shard(key) as u64);
+                    }
+                }
+            }
+        }
+
+        // Counts past `u32::MAX` reach the whole range.
+        let shard = FastShard::new(16);
+        let count = 1u64 << 40;
+        let placed: Vec<u64> = keys.iter().map(|key| shard.shard64(key, count)).collect();
+        assert!(placed.iter().all(|&s| s < count));
+        assert!(placed.iter().filter(|&&s| s > u32::MAX as u64).count() > keys.len() * 9 / 10);
+        let odd = 5 * (1u64 << 32) + 3;
+        for key in &keys {
+            assert_eq!(shard.shard64(key, odd), shard.hash64(key) % odd);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "past u16::MAX")]
+    fn test_shard16_rejects_wide_counts() {
+        FastShard::new(65_537).shard16(b"key");
+    }
+
+    #[test]
+    fn test_shard_is_reduced_hash64() {
+        let keys: Vec<Vec<u8>> = (0..200usize)
+            .map(|len| (0..len).map(|i| (i * 37 + len) as u8).collect())
+            .collect();
+        let configs = info::ALGORITHMS
+            .into_iter()
+            .filter(ShardAlgorithm::is_enabled)
+            .map(ShardConfig::single)
+            .chain([ShardConfig::default()]);
+        for config in configs {
+            let shard = FastShard::with_config(1000, config);
+            for key in &keys {
+                let hash = shard.hash64(key);
+                assert_eq!(shard.shard(key), shard.reduce(hash));
+                assert_eq!(shard.hash_and_shard(key), (hash, shard.shard(key)));
+            }
+        }
+
+        // The SIMD kernels' 32-bit hashes are finalized to 64 bits.
+        #[cfg(target_arch = "x86_64")]
+        if ShardAlgorithm::Avx2.is_enabled() && cpu::CpuFeatures::detect().avx2 {
+            let avx2 = FastShard::with_config(1000, ShardConfig::single(ShardAlgorithm::Avx2));
+            for key in &keys {
+                assert_eq!(avx2.hash64(key), mix::finalize(reference::avx2_hash(key) as u64));
+            }
+            assert!(keys.iter().any(|key| avx2.hash64(key) > u32::MAX as u64));
+        }
+    }
+
+    #[test]
+    fn test_seeds_place_keys_independently() {
+        use rand::{Rng, SeedableRng};
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(264);
+        let keys: Vec<Vec<u8>> = (0..1000)
+            .map(|_| {
+                let len = rng.gen_range(0..100);
+                (0..len).map(|_| rng.gen()).collect()
+            })
+            .collect();
+        let configs = info::ALGORITHMS
+            .into_iter()
+            .filter(ShardAlgorithm::is_enabled)
+            .map(ShardConfig::single)
+            .chain([ShardConfig::default()]);
+        for config in configs {
+            let seeded = |seed| FastShard::with_config(1024, ShardConfig { seed, ..config.clone() });
+            let (unseeded, zero, one, two) =
+                (FastShard::with_config(1024, config.clone()), seeded(0), seeded(1), seeded(2));
+            let disagree = keys.iter().filter(|key| one.shard(key) != two.shard(key)).count();
+            assert!(disagree >= 400, "{:?}: {} of 1000 differ", config.tiers[0].algorithms, disagree);
+            assert!(keys.iter().all(|key| zero.shard(key) == unseeded.shard(key)));
+
+            let iovecs: Vec<&[u8]> = keys[7].chunks(5).collect();
+            assert_eq!(one.shard_iovecs(&iovecs), one.shard(&keys[7]));
+        }
+    }
+
+    #[test]
+    fn test_shard_bytes_and_u64() {
+        let shard = FastShard::new(1024);
+        assert_eq!(shard.shard_bytes("x"), shard.shard(b"x"));
+        assert_eq!(shard.shard_bytes(String::from("hello")), shard.shard(b"hello"));
+        assert_eq!(shard.shard_bytes(vec![1u8, 2, 3]), shard.shard(&[1, 2, 3]));
+        let arr = [7u8; 20];
+        assert_eq!(shard.shard_bytes(arr), shard.shard(&arr));
+        assert_eq!(shard.shard_bytes(&arr[..]), shard.shard(&arr));
+        assert_eq!(shard.shard_bytes(""), shard.shard(b""));
+
+        for key in [0u64, 1, 42, 0x0123_4567_89ab_cdef, u64::MAX] {
+            assert_eq!(shard.shard_u64(key), shard.shard(&key.to_le_bytes()));
+        }
+    }
+
+    // On aarch64 hosts with the AES extension this runs the NEON kernel,
+    // which must match the AES-NI model; elsewhere the config falls back.
+    #[test]
+    fn test_neon_distribution() {
+        let shard = FastShard::with_config(256, ShardConfig::single(ShardAlgorithm::Neon));
+        let available = cpu::CpuFeatures::detect().neon;
+        assert!(!available || cfg!(all(target_arch = "aarch64", feature = "neon")));
+        let expected_algorithm = if available { ShardAlgorithm::Neon } else { fallback_algorithm() };
+        for len in [0, 8, 16, 17, 1000] {
+            assert_eq!(shard.which_algorithm(len), expected_algorithm);
+        }
+
+        let samples = 100_000u64;
+        let mut counts = [0u64; 256];
+        for i in 0..samples {
+            let key = mix::splitmix64(i).to_le_bytes();
+            let placed = shard.shard(&key);
+            if available {
+                assert_eq!(placed, simd_shard(reference::aesni_hash(&key), 256));
+            }
+            counts[placed as usize] += 1;
+        }
+        assert_uniform(&counts);
+    }
+
+    #[test]
+    fn test_wyhash_distribution() {
+        let shard = FastShard::with_config(256, ShardConfig::single(ShardAlgorithm::Wyhash));
+        let expected_algorithm =
+            if cfg!(feature = "wyhash") { ShardAlgorithm::Wyhash } else { fallback_algorithm() };
+        assert_eq!(shard.which_algorithm(8), expected_algorithm);
+
+        let samples = 100_000u64;
+        let mut counts = [0u64; 256];
+        for i in 0..samples {
+            let key = mix::splitmix64(i).to_le_bytes();
+            let placed = shard.shard(&key);
+            #[cfg(feature = "wyhash")]
+            assert_eq!(placed as u64, wyhash::wyhash(&key, 0) % 256);
+            counts[placed as usize] += 1;
+        }
+        assert_uniform(&counts);
+    }
+
+    #[test]
+    #[cfg(feature = "wyhash")]
+    fn test_wyhash_respects_seed() {
+        let config = ShardConfig { seed: 268, ..ShardConfig::single(ShardAlgorithm::Wyhash) };
+        let shard = FastShard::with_config(1000, config);
+        for key in [&b""[..], b"a", b"sixteen-byte-key", b"a key longer than thirty-two bytes"] {
+            assert_eq!(shard.hash64(key), wyhash::wyhash(key, 268));
+        }
+    }
+
+    // An attacker who knows the seed (here 0) can mine keys that all land on
+    // one shard; under a seed they don't know, the same keys spread evenly.
+    #[test]
+    #[cfg(feature = "siphash")]
+    fn test_secure_config_spreads_attacker_chosen_keys() {
+        use rand::{Rng, SeedableRng};
+
+        let known = FastShard::with_config(64, ShardConfig::secure(0));
+        let flood: Vec<String> = (0u64..)
+            .map(|i| format!("user-{}", i))
+            .filter(|key| known.shard(key.as_bytes()) == 0)
+            .take(6400)
+            .collect();
+
+        let seed = rand::rngs::StdRng::seed_from_u64(270).gen::<u64>();
+        let config = ShardConfig::secure(seed);
+        assert_eq!(config.validate(), Ok(()));
+        let shard = FastShard::with_config(64, config);
+        assert_eq!(shard.which_algorithm(8), ShardAlgorithm::SipHash13);
+
+        let mut counts = [0u64; 64];
+        for key in &flood {
+            counts[shard.shard(key.as_bytes()) as usize] += 1;
+        }
+        assert_uniform(&counts);
+    }
+
+    // WASM has no runtime detection, so a build either runs the kernel or
+    // was compiled without SIMD128 and falls back.
+    #[test]
+    #[cfg(target_arch = "wasm32")]
+    fn test_simd128_runs_without_trapping() {
+        let shard = FastShard::with_config(1021, ShardConfig::single(ShardAlgorithm::Simd128));
+        let kernel = cfg!(all(target_feature = "simd128", feature = "simd128"));
+        let expected = if kernel { ShardAlgorithm::Simd128 } else { fallback_algorithm() };
+        assert_eq!(shard.which_algorithm(100), expected);
+        assert_eq!(ShardConfig::default().tiers[0].algorithms.contains(&ShardAlgorithm::Simd128), kernel);
+
+        let key: Vec<u8> = (0..=255u8).collect();
+        for len in 0..=200 {
+            let placed = shard.shard(&key[..len]);
+            assert!(placed < 1021);
+            if kernel {
+                assert_eq!(placed, simd_shard(reference::avx512_hash(&key[..len]), 1021));
+            }
+        }
+        #[cfg(all(target_feature = "simd128", feature = "simd128"))]
+        for len in 0..=200 {
+            let key = &key[..len];
+            assert_eq!(wasm::simd128_hash_seeded(key, 264), reference::avx512_hash_seeded(key, 264));
+        }
+    }
+
+    #[test]
+    fn test_simd128_falls_back_off_wasm() {
+        if cfg!(target_arch = "wasm32") {
+            return;
+        }
+        let shard = FastShard::with_config(1021, ShardConfig::single(ShardAlgorithm::Simd128));
+        assert_eq!(shard.which_algorithm(100), fallback_algorithm());
+        assert!(!ShardConfig::default().tiers[0].algorithms.contains(&ShardAlgorithm::Simd128));
+    }
+
+    #[test]
+    #[cfg(all(target_arch = "aarch64", feature = "neon"))]
+    fn test_neon_kernel_matches_aesni_model_when_detected() {
+        if !std::arch::is_aarch64_feature_detected!("aes") {
+            return;
+        }
+        for key in cross_check_keys() {
+            assert_eq!(unsafe { neon::neon_hash(&key) }, reference::aesni_hash(&key));
+            for seed in [1, 0xdead_beef_cafe_f00d] {
+                let neon = unsafe { neon::neon_hash_seeded(&key, seed) };
+                assert_eq!(neon, reference::aesni_hash_seeded(&key, seed));
+            }
+        }
+    }
+
+    #[test]
+    fn test_shard_u128() {
+        let shard = FastShard::new(1024);
+        let addrs = [0u128, 1, 0x2001_0db8_85a3_0000_0000_8a2e_0370_7334, u128::MAX];
+        for addr in addrs {
+            assert_eq!(shard.shard_u128(addr), shard.shard(&addr.to_le_bytes()));
+        }
+
+        // On a host with AES-NI compiled in, 16-byte keys take the AES kernel.
+        #[cfg(target_arch = "x86_64")]
+        if cpu::CpuFeatures::detect().aes {
+            let aes = FastShard::with_config(1024, ShardConfig::single(ShardAlgorithm::AesNi));
+            for addr in addrs {
+                let expected = simd_shard(reference::aesni_hash(&addr.to_le_bytes()), 1024);
+                assert_eq!(aes.shard_u128(addr), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_salt_with_shard_count_decorrelates_reshards() {
+        // Without the salt, hash % 2N % N == hash % N, so a key's N-shard is
+        // fully determined by its 2N-shard.
+        let nested_fraction = |salt: bool| {
+            let config = ShardConfig {
+                salt_with_shard_count: salt,
+                ..ShardConfig::single(ShardAlgorithm::Xxh3)
+            };
+            let n = FastShard::with_config(16, config.clone());
+            let two_n = FastShard::with_config(32, config);
+            let nested = (0..4000u32)
+                .filter(|i| {
+                    let key = format!("key-{}", i);
+                    two_n.shard(key.as_bytes()) % 16 == n.shard(key.as_bytes())
+                })
+                .count();
+            nested as f64 / 4000.0
+        };
+
+        assert_eq!(nested_fraction(false), 1.0);
+        // Independent placements coincide for about 1/16 of keys.
+        assert!(nested_fraction(true) < 0.15);
+    }
+
+    #[test]
+    #[cfg(feature = "fnv")]
+    fn test_inline_fnv1a_matches_fnv_crate() {
+        use std::hash::Hasher;
+        for len in 0..=100usize {
+            let key: Vec<u8> = (0..len).map(|i| (i * 37 + len) as u8).collect();
+            let mut hasher = fnv::FnvHasher::default();
+            hasher.write(&key);
+            assert_eq!(fnv1a_64(&key, 0), hasher.finish(), "len {}", len);
+        }
+    }
+
+    #[test]
+    fn test_shard_skip_prefix() {
+        let shard = FastShard::new(1024);
+        let mut differing = 0;
+        for i in 0..200u32 {
+            let a = format!("tenant-aaaa/{:05}", i);
+            let b = format!("tenant-bbbb/{:05}", i);
+            let c = format!("tenant-aaaa/{:05}", i + 1);
+            assert_eq!(
+                shard.shard_skip_prefix(a.as_bytes(), 12),
+                shard.shard_skip_prefix(b.as_bytes(), 12)
+            );
+            if shard.shard_skip_prefix(a.as_bytes(), 12) != shard.shard_skip_prefix(c.as_bytes(), 12) {
+                differing += 1;
+            }
+        }
+        assert!(differing > 190, "only {} of 200 suffix changes moved the key", differing);
+
+        // The length still counts, and a skip past the end is allowed.
+        assert_ne!(shard.shard_skip_prefix(b"xx", 4), shard.shard_skip_prefix(b"xxx", 4));
+    }
+
+    #[test]
+    fn test_shard_pair_is_order_sensitive() {
+        let shard = FastShard::new(1024);
+        let swapped_differs = (0..500u32)
+            .filter(|i| {
+                let user = format!("user-{}", i);
+                let object = format!("object-{}", i * 7);
+                shard.shard_pair(user.as_bytes(), object.as_bytes())
+                    != shard.shard_pair(object.as_bytes(), user.as_bytes())
+            })
+            .count();
+        assert!(swapped_differs > 490, "only {} of 500 swaps moved", swapped_differs);
+
+        assert_eq!(shard.shard_pair(b"u", b"o"), shard.shard_pair(b"u", b"o"));
+        // XOR would cancel identical parts down to hash 0.
+        assert_ne!(shard.shard_pair(b"x", b"x"), shard.reduce(0));
+    }
+
+    #[test]
+    fn test_prefixes_shard_independently() {
+        let shard = FastShard::new(64);
+        let keys: Vec<String> = (0..4096).map(|i| format!("order-{}", i)).collect();
+        let place = |tenant: &[u8]| -> Vec<u32> {
+            keys.iter().map(|key| shard.shard_with_prefix(tenant, key.as_bytes())).collect()
+        };
+        let (tenant_a, tenant_b) = (place(b"tenant-a"), place(b"tenant-b"));
+
+        // Independent placements agree on about 1 key in 64.
+        let agree = tenant_a.iter().zip(&tenant_b).filter(|(a, b)| a == b).count();
+        assert!(agree < keys.len() / 32, "{} of {} keys agree", agree, keys.len());
+
+        // The keys one tenant piles on a shard scatter for the other.
+        let mut spread: Vec<u32> = (0..keys.len())
+            .filter(|&i| tenant_a[i] == 0)
+            .map(|i| tenant_b[i])
+            .collect();
+        spread.sort_unstable();
+        spread.dedup();
+        assert!(spread.len() > 30, "{} shards", spread.len());
+
+        assert_eq!(shard.shard_with_prefix(b"t", b"k"), shard.shard_pair(b"t", b"k"));
+    }
+
+    #[test]
+    fn test_shard_of_u32_is_uniform() {
+        use rand::{Rng, SeedableRng};
+
+        let shard = FastShard::new(100);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(218);
+        let samples = 100_000;
+        let mut counts = [0u64; 100];
+        for _ in 0..samples {
+            counts[shard.shard_of_u32(rng.gen()) as usize] += 1;
+        }
+        assert_uniform(&counts);
+        assert_eq!(shard.shard_of_u32(12345), 45);
+    }
+
+    // Modulo bias shows once hashes are narrow: a 2^20-value range over 1000
+    // shards leaves 576 shards one key over. Per shard both modes produce
+    // the same counts, only in different places, so the chi-square runs over
+    // blocks of 100 shards: modulo stacks the excess on the low ids, while
+    // multiply spreads it evenly.
+    #[test]
+    fn test_multiply_reduction_flattens_narrow_hashes() {
+        let block_chi_square = |reduction: ReductionMode| {
+            let config = ShardConfig {
+                reduction,
+                ..ShardConfig::default()
+            };
+            let shard = FastShard::with_config(1000, config);
+            let mut counts = vec![0u64; 1000];
+            // Every 20-bit value, in the bits each mode reads.
+            for h in 0..1u32 << 20 {
+                let hash32 = match reduction {
+                    ReductionMode::Modulo => h,
+                    ReductionMode::Multiply => h << 12,
+                };
+                counts[shard.shard_of_u32(hash32) as usize] += 1;
+            }
+            assert!(counts.iter().all(|&c| c == 1048 || c == 1049));
+            let blocks: Vec<u64> = counts.chunks(100).map(|block| block.iter().sum()).collect();
+            chi_square(&blocks)
+        };
+        let modulo = block_chi_square(ReductionMode::Modulo);
+        let multiply = block_chi_square(ReductionMode::Multiply);
+        assert!(modulo > 0.2, "modulo: {}", modulo);
+        assert!(multiply < modulo / 100.0, "multiply: {} vs modulo: {}", multiply, modulo);
+
+        let config = ShardConfig {
+            reduction: ReductionMode::Multiply,
+            ..ShardConfig::default()
+        };
+        let shard = FastShard::with_config(1000, config);
+        assert_eq!(shard.shard_of_u32(0), 0);
+        assert_eq!(shard.shard_of_u32(u32::MAX), 999);
+        assert_eq!(shard.shard_of_u32(1 << 31), 500);
+    }
+
+    #[test]
+    fn test_multiply_spreads_simd_hashes() {
+        // The SIMD kernels' 32-bit hashes are finalized to 64 bits, so
+        // multiply reduction sees high bits without a config finalizer.
+        for algorithm in [ShardAlgorithm::Avx512, ShardAlgorithm::Avx2, ShardAlgorithm::AesNi] {
+            let config = ShardConfig {
+                reduction: ReductionMode::Multiply,
+                ..ShardConfig::single(algorithm.clone())
+            };
+            assert_eq!(config.validate().is_ok(), algorithm.is_enabled());
+            if !algorithm.is_enabled() {
+                continue;
+            }
+            let shard = FastShard::with_config(1000, config);
+            let hit: std::collections::HashSet<u32> =
+                (0..5_000).map(|i| shard.shard(format!("key-{}", i).as_bytes())).collect();
+            assert!(hit.len() > 900, "{:?}: {} shards hit", algorithm, hit.len());
+        }
+    }
+
+    #[test]
+    fn test_try_from_components() {
+        let shard = FastShard::try_from((64, ShardConfig::default())).unwrap();
+        assert!(shard.shard(b"hello") < 64);
+
+        assert_eq!(
+            FastShard::try_from((0, ShardConfig::default())).unwrap_err(),
+            ShardError::ZeroShardCount
+        );
+
+        let overlapping = ShardConfig {
+            tiers: vec![
+                ShardTier {
+                    size_range: 0..=32,
+                    algorithms: vec![fallback_algorithm()],
+                },
+                ShardTier {
+                    size_range: 16..=usize::MAX,
+                    algorithms: vec![fallback_algorithm()],
+                },
+            ],
+            ..ShardConfig::default()
+        };
+        assert_eq!(
+            FastShard::try_from((64, overlapping)).unwrap_err(),
+            ShardError::InvalidConfig(ConfigError::Overlap {
+                first: 0..=32,
+                second: 16..=usize::MAX,
+            })
+        );
+    }
+
+    #[test]
+    fn test_set_shard_count_resizes_in_place() {
+        let keys: Vec<String> = (0..2_000).map(|i| format!("key-{}", i)).collect();
+        let mut shard = FastShard::new(100).with_metrics();
+        assert_eq!(shard.shard_count(), 100);
+        for key in &keys {
+            shard.shard(key.as_bytes());
+        }
+
+        // 128 exercises the power-of-two mask, 1000 the general path.
+        for count in [128, 1000, 1] {
+            shard.set_shard_count(count).unwrap();
+            assert_eq!(shard.shard_count(), count);
+            assert_eq!(shard.shard_counts(), vec![0; count as usize]);
+            let fresh = FastShard::new(count);
+            for key in &keys {
+                assert_eq!(shard.shard(key.as_bytes()), fresh.shard(key.as_bytes()));
+            }
+            assert_eq!(shard.shard_counts().iter().sum::<u64>(), keys.len() as u64);
+        }
+
+        assert_eq!(shard.set_shard_count(0), Err(ShardError::ZeroShardCount));
+        assert_eq!(shard.shard_count(), 1);
+        assert_eq!(shard.shard(b"still works"), 0);
+    }
+
+    #[test]
+    fn test_fallback_order_skips_unsupported_kernels() {
+        let order = [
+            ShardAlgorithm::Avx512,
+            ShardAlgorithm::Avx2,
+            ShardAlgorithm::AesNi,
+            ShardAlgorithm::Wyhash,
+            ShardAlgorithm::Fnv1a,
+            ShardAlgorithm::Xxh3,
+        ];
+        let config = ShardConfig {
+            tiers: vec![ShardTier {
+                size_range: 0..=usize::MAX,
+                algorithms: order.to_vec(),
+            }],
+            ..ShardConfig::default()
+        };
+        let detected = cpu::CpuFeatures::detect();
+        let key = b"fallback-order-288";
+        // Mask out every subset of the detected x86 kernels; masking only
+        // ever removes features, so the kernels left are safe to run.
+        for mask in 0..8u8 {
+            let cpu = cpu::CpuFeatures {
+                avx512f: detected.avx512f && mask & 1 == 0,
+                avx2: detected.avx2 && mask & 2 == 0,
+                aes: detected.aes && mask & 4 == 0,
+                ..detected
+            };
+            let mut shard = FastShard::with_config(1021, config.clone());
+            shard.cpu = cpu;
+            let expected = order.iter().find(|algo| cpu.supports(algo)).unwrap().clone();
+            assert_eq!(shard.which_algorithm(key.len()), expected, "mask {:03b}", mask);
+            assert_eq!(shard.hash64(key), shard.hash_with_algorithm(key, expected));
+        }
+
+        let mut none = FastShard::with_config(1021, config);
+        none.cpu = cpu::CpuFeatures::default();
+        let scalar = order[3..].iter().find(|algo| algo.is_enabled()).unwrap();
+        assert_eq!(&none.which_algorithm(key.len()), scalar);
+    }
+
+    #[test]
+    fn test_try_new_rejects_zero_shards() {
+        assert_eq!(FastShard::try_new(0).unwrap_err(), ShardError::ZeroShardCount);
+        let single = FastShard::try_new(1).unwrap();
+        assert_eq!(single.shard(b"anything"), 0);
+        assert!(FastShard::try_new(u32::MAX).is_ok());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "shard_count must be nonzero")]
+    fn test_new_zero_panics_at_construction() {
+        FastShard::new(0);
+    }
+
+    #[test]
+    fn test_try_with_config_reports_layout_errors() {
+        let tier = |size_range: RangeInclusive<usize>, algorithms: Vec<ShardAlgorithm>| ShardTier {
+            size_range,
+            algorithms,
+        };
+        let with_tiers = |tiers: Vec<ShardTier>| ShardConfig {
+            tiers,
+            ..ShardConfig::default()
+        };
+        let algos = || vec![fallback_algorithm()];
+
+        let overlap = with_tiers(vec![tier(0..=64, algos()), tier(32..=usize::MAX, algos())]);
+        assert_eq!(
+            FastShard::try_with_config(64, overlap).unwrap_err(),
+            ShardError::InvalidConfig(ConfigError::Overlap {
+                first: 0..=64,
+                second: 32..=usize::MAX,
+            })
+        );
+
+        let gap = with_tiers(vec![tier(0..=16, algos()), tier(64..=usize::MAX, algos())]);
+        assert_eq!(
+            FastShard::try_with_config(64, gap).unwrap_err(),
+            ShardError::InvalidConfig(ConfigError::Gap(17..=63))
+        );
+        let short = with_tiers(vec![tier(0..=16, algos())]);
+        assert_eq!(short.validate(), Err(ConfigError::Gap(17..=usize::MAX)));
+        let no_zero = with_tiers(vec![tier(1..=usize::MAX, algos())]);
+        assert_eq!(no_zero.validate(), Err(ConfigError::Gap(0..=0)));
+
+        let empty = with_tiers(vec![tier(0..=16, algos()), tier(17..=usize::MAX, vec![])]);
+        assert_eq!(
+            FastShard::try_with_config(64, empty).unwrap_err(),
+            ShardError::InvalidConfig(ConfigError::EmptyAlgorithms(17..=usize::MAX))
+        );
+
+        let shard = FastShard::try_with_config(64, ShardConfig::default()).unwrap();
+        assert!(shard.shard(b"hello") < 64);
+    }
+
+    #[test]
+    fn test_shard_with_key_fn_matches_assembled_key() {
+        // The default config gathers; a single-algorithm one streams.
+        let configs = [ShardConfig::default(), ShardConfig::single(fallback_algorithm())];
+        for shard in configs.map(|config| FastShard::with_config(1024, config).with_metrics()) {
+            for fields in [&["tenant", ":", "42"][..], &[][..], &["a"; 40][..]] {
+                let assembled: Vec<u8> = fields.concat().into_bytes();
+                let streamed = shard.shard_with_key_fn(|sink| {
+                    for field in fields {
+                        sink(field.as_bytes());
+                    }
+                });
+                assert_eq!(streamed, shard.shard(&assembled));
+            }
+            assert_eq!(shard.shard_counts().iter().sum::<u64>(), 6);
+        }
+    }
+
+    // 64 MiB: a million AVX-512 chunks and four million AES blocks, so any
+    // running-hash or chunk-index mistake shows up against the one-shot
+    // references.
+    #[test]
+    fn test_large_key_is_stable_across_chunks() {
+        let mut x = 0x2545_f491_4f6c_dd1du64;
+        let key: Vec<u8> = (0..64 << 20)
+            .map(|_| {
+                x = x.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                (x >> 56) as u8
+            })
+            .collect();
+
+        let shard = FastShard::new(1021);
+        let placed = shard.shard(&key);
+        assert!(placed < 1021);
+        assert_eq!(placed, shard.shard(&key));
+
+        #[cfg(feature = "xxh3")]
+        {
+            let mut streaming = xxhash_rust::xxh3::Xxh3::new();
+            for chunk in key.chunks(1 << 20) {
+                streaming.update(chunk);
+            }
+            let xxh3 = FastShard::with_config(1021, ShardConfig::single(ShardAlgorithm::Xxh3));
+            assert_eq!(xxh3.shard(&key) as u64, streaming.digest() % 1021);
+        }
+
+        #[cfg(feature = "fnv")]
+        {
+            use std::hash::Hasher;
+            let mut streaming = fnv::FnvHasher::default();
+            for chunk in key.chunks(1 << 20) {
+                streaming.write(chunk);
+            }
+            assert_eq!(fnv1a_64(&key, 0), streaming.finish());
+        }
+
+        #[cfg(all(target_arch = "x86_64", feature = "avx512"))]
+        if is_x86_feature_detected!("avx512f") {
+            assert_eq!(unsafe { simd::avx512_hash(&key) }, reference::avx512_hash(&key));
+        }
+        #[cfg(all(target_arch = "x86_64", feature = "avx2"))]
+        if is_x86_feature_detected!("avx2") {
+            assert_eq!(unsafe { simd::avx2_hash(&key) }, reference::avx2_hash(&key));
+        }
+        #[cfg(all(target_arch = "x86_64", feature = "aesni"))]
+        if is_x86_feature_detected!("aes") {
+            assert_eq!(unsafe { simd::aesni_hash(&key) }, reference::aesni_hash(&key));
+        }
+    }
+
+    #[test]
+    fn test_tier_selection_policies() {
+        let mut config = ShardConfig {
+            tiers: vec![
+                ShardTier {
+                    size_range: 0..=1024,
+                    algorithms: vec![fallback_algorithm()],
+                },
+                ShardTier {
+                    size_range: 8..=16,
+                    algorithms: vec![fallback_algorithm()],
+                },
+            ],
+            ..ShardConfig::default()
+        };
+        assert!(matches!(config.validate(), Err(ConfigError::Overlap { .. })));
+        let first = FastShard::with_config(64, config.clone());
+        assert_eq!(first.tier_index(12), Some(0));
+
+        config.tier_selection = TierSelection::NarrowestMatch;
+        let narrowest = FastShard::with_config(64, config.clone());
+        assert_eq!(narrowest.tier_index(12), Some(1));
+        assert_eq!(narrowest.tier_index(4), Some(0));
+        assert_eq!(narrowest.tier_index(2048), None);
+        // Nesting is allowed, but sizes past the wide tier are uncovered.
+        assert_eq!(config.validate(), Err(ConfigError::Gap(1025..=usize::MAX)));
+        config.tiers[0].size_range = 0..=usize::MAX;
+        assert_eq!(config.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_single_shard_skips_hashing() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&calls);
+        let shard = FastShard::with_hasher(1, move |key| {
+            counter.fetch_add(1, Ordering::Relaxed);
+            key.len() as u64
+        });
+        for key in [&b""[..], b"a", b"fast-shard", &[0xab; 4096]] {
+            assert_eq!(shard.shard(key), 0);
+        }
+        assert_eq!(calls.load(Ordering::Relaxed), 0);
+
+        let plain = FastShard::new(1);
+        assert!((0..1000u32).all(|i| plain.shard(&i.to_le_bytes()) == 0));
+    }
+
+    #[test]
+    fn test_fixed_length_hashes_the_bytes_alone() {
+        let uuid = *b"0123456789abcdef";
+        let models = [
+            (ShardAlgorithm::Avx512, reference::avx512_hash_with_len as fn(&[u8], u64, usize) -> u32),
+            (ShardAlgorithm::Avx2, reference::avx2_hash_with_len),
+            (ShardAlgorithm::AesNi, reference::aesni_hash_with_len),
+        ];
+        for (algo, model) in models {
+            let plain = FastShard::with_config(1021, ShardConfig::single(algo.clone()));
+            let config = ShardConfig {
+                fixed_length: Some(16),
+                ..ShardConfig::single(algo.clone())
+            };
+            let fixed = FastShard::with_config(1021, config);
+            // Without the kernel, the fallback has no length fold to drop.
+            if fixed.which_algorithm(16) != algo {
+                assert_eq!(fixed.hash64(&uuid), plain.hash64(&uuid));
+                continue;
+            }
+            assert_eq!(fixed.hash64(&uuid), mix::finalize(model(&uuid, 0, 0) as u64), "{:?}", algo);
+            assert_eq!(plain.hash64(&uuid), mix::finalize(model(&uuid, 0, 16) as u64), "{:?}", algo);
+            assert_ne!(fixed.hash64(&uuid), plain.hash64(&uuid), "{:?}", algo);
+            let mut out = [0u32; 1];
+            fixed.shard_batch_by_tier(&[&uuid[..]], &mut out);
+            assert_eq!(out[0], fixed.shard(&uuid), "{:?}", algo);
+        }
+
+        let config = ShardConfig {
+            fixed_length: Some(16),
+            ..ShardConfig::default()
+        };
+        let fixed = FastShard::with_config(1021, config);
+        assert_eq!(fixed.try_shard(&uuid), Ok(fixed.shard(&uuid)));
+
+        // The length-mixing step in `shard_skip_prefix` is dropped.
+        assert_eq!(fixed.shard_skip_prefix(&uuid, 4), fixed.reduce(fixed.hash64(&uuid[4..])));
+
+        assert_eq!(
+            fixed.try_shard(b"too short"),
+            Err(ShardError::KeyLength {
+                expected: 16,
+                actual: 9,
+            })
+        );
+    }
+
+    #[test]
+    fn test_min_key_len_pads_and_mixes_length() {
+        let config = ShardConfig {
+            min_key_len: Some(16),
+            ..ShardConfig::default()
+        };
+        let padded = FastShard::with_config(1 << 20, config);
+        let plain = FastShard::new(1 << 20);
+
+        let short = padded.shard(b"abc");
+        assert_eq!(short, padded.shard(b"abc"));
+        assert_ne!(short, padded.shard(b"abc\0\0\0\0\0\0\0\0\0\0\0\0\0"));
+        assert_ne!(short, padded.shard(b"abc\0"));
+        assert_ne!(padded.shard(b""), padded.shard(&[0u8; 16]));
+
+        let full = b"abcdefghijklmnopq";
+        assert_eq!(padded.shard(full), plain.shard(full));
+        let keys: [&[u8]; 2] = [b"abc", full];
+        let mut out = [0u32; 2];
+        padded.shard_batch_by_tier(&keys, &mut out);
+        assert_eq!(out, [short, plain.shard(full)]);
+    }
+
+    // These placements are part of the crate's contract; they must never
+    // change.
+    #[test]
+    #[cfg(feature = "xxh3")]
+    fn test_deterministic_config_placements() {
+        let config = ShardConfig::deterministic();
+        assert_eq!(config.validate(), Ok(()));
+        let small = FastShard::with_config(1024, config.clone());
+        let large = FastShard::with_config(1_000_003, config);
+        for len in [0, 8, 16, 17, 64, 100_000] {
+            assert_eq!(small.which_algorithm(len), ShardAlgorithm::Xxh3);
+        }
+
+        let expected: [(&[u8], u32, u32); 6] = [
+            (b"", 194, 778_394),
+            (b"a", 543, 103_908),
+            (b"user:42", 170, 160_471),
+            (b"order-2026-10-16", 85, 699_147),
+            (b"tenant-7/photos/cat.jpg", 549, 703_357),
+            (b"a much longer key that spans more than sixteen bytes and tiers", 774, 608_224),
+        ];
+        for (key, in_1024, in_1_000_003) in expected {
+            assert_eq!(small.shard(key), in_1024, "{:?}", key);
+            assert_eq!(large.shard(key), in_1_000_003, "{:?}", key);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "xxh3")]
+    fn test_single_config_routes_every_size() {
+        let config = ShardConfig::single(ShardAlgorithm::Xxh3);
+        assert_eq!(config.validate(), Ok(()));
+        let shard = FastShard::with_config(1021, config);
+        for len in [0, 1, 4, 15, 16, 17, 64, 100, 4096, 100_000] {
+            assert_eq!(shard.which_algorithm(len), ShardAlgorithm::Xxh3);
+            let key = vec![0x5a; len];
+            let expected = (xxhash_rust::xxh3::xxh3_64(&key) % 1021) as u32;
+            assert_eq!(shard.shard(&key), expected, "len {}", len);
+        }
+    }
+
+    #[test]
+    fn test_presets_are_valid_across_sizes() {
+        use std::collections::HashSet;
+
+        for config in [ShardConfig::latency_optimized(), ShardConfig::quality_optimized()] {
+            assert_eq!(config.validate(), Ok(()));
+            assert!(config.tiers.iter().all(|tier| !tier.algorithms.is_empty()));
+            let shard = FastShard::with_config(1021, config.clone());
+
+            let mut len = 4;
+            while len <= 32768 {
+                for len in [len, len + 1, len + 3] {
+                    let key: Vec<u8> = (0..len).map(|i| (i * 31 + len) as u8).collect();
+                    let algorithm = shard.which_algorithm(len);
+                    assert!(capabilities().contains(&algorithm), "{:?} at {}", algorithm, len);
+                    assert!(shard.shard(&key) < 1021);
+                }
+                len *= 2;
+            }
+
+            // Well spread on sequential keys through either tier.
+            for prefix in ["k", "a-key-longer-than-sixteen-bytes/"] {
+                let shards: HashSet<u32> =
+                    (0..20_000).map(|i| shard.shard(format!("{}{}", prefix, i).as_bytes())).collect();
+                assert!(shards.len() > 1000, "{} shards hit", shards.len());
+            }
+        }
+
+        let quality = FastShard::with_config(64, ShardConfig::quality_optimized());
+        let strength = |algorithm: ShardAlgorithm| {
+            algorithm_info().into_iter().find(|info| info.algorithm == algorithm).unwrap().strength
+        };
+        for len in [0, 8, 16, 17, 32768] {
+            assert_ne!(strength(quality.which_algorithm(len)), HashStrength::Weak, "len {}", len);
+        }
+        #[cfg(feature = "xxh3")]
+        {
+            let latency = FastShard::with_config(64, ShardConfig::latency_optimized());
+            assert_eq!(latency.which_algorithm(64), ShardAlgorithm::Xxh3);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_config_serde_round_trip() {
+        let config = ShardConfig::default();
+        let json = serde_json::to_string(&config).unwrap();
+        assert_eq!(serde_json::from_str::<ShardConfig>(&json).unwrap(), config);
+
+        for info in crate::algorithm_info() {
+            let name = serde_json::to_value(&info.algorithm).unwrap();
+            assert_eq!(name, serde_json::Value::from(info.name));
+        }
+
+        let parsed: ShardConfig = serde_json::from_str(
+            r#"{
+                "tiers": [
+                    { "size_range": { "start": 0, "end": 16 }, "algorithms": ["avx2", "xxh3"] },
+                    { "size_range": { "start": 17, "end": 4096 }, "algorithms": ["xxh3"] }
+                ],
+                "finalizer": "fmix64"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(parsed.tiers[0].size_range, 0..=16);
+        assert_eq!(parsed.tiers[0].algorithms, vec![ShardAlgorithm::Avx2, ShardAlgorithm::Xxh3]);
+        assert_eq!(parsed.finalizer, Finalizer::Fmix64);
+        assert_eq!(parsed.default_algorithms, ShardConfig::default().default_algorithms);
+    }
+
+    #[test]
+    #[cfg(feature = "toml")]
+    fn test_config_from_toml() {
+        let text = r#"
+            # Sharding policy for the session store.
+            seed = 42
+            reduction = "multiply"
+            default_algorithms = ["xxh3"]
+
+            [[tiers]]
+            size_range = { start = 0, end = 16 }
+            algorithms = ["avx2", "fnv1a"]
+
+            [[tiers]]
+            size_range = { start = 17, end = 1024 }
+            algorithms = ["aesni", "xxh3"]
+
+            [[tiers]]
+            size_range = { start = 1025 }
+            algorithms = ["xxh3"]
+        "#;
+        let config = ShardConfig::from_toml_str(text).unwrap();
+        let expected = ShardConfig {
+            tiers: vec![
+                ShardTier {
+                    size_range: 0..=16,
+                    algorithms: vec![ShardAlgorithm::Avx2, ShardAlgorithm::Fnv1a],
+                },
+                ShardTier {
+                    size_range: 17..=1024,
+                    algorithms: vec![ShardAlgorithm::AesNi, ShardAlgorithm::Xxh3],
+                },
+                ShardTier {
+                    size_range: 1025..=usize::MAX,
+                    algorithms: vec![ShardAlgorithm::Xxh3],
+                },
+            ],
+            default_algorithms: vec![ShardAlgorithm::Xxh3],
+            reduction: ReductionMode::Multiply,
+            seed: 42,
+            ..ShardConfig::default()
+        };
+        assert_eq!(config, expected);
+
+        let loaded = FastShard::with_config(97, config);
+        let built = FastShard::with_config(97, expected);
+        for len in [0, 5, 16, 17, 100, 1024, 1025, 5000] {
+            let key: Vec<u8> = (0..len).map(|i| (i * 7 + len) as u8).collect();
+            assert_eq!(loaded.which_algorithm(len), built.which_algorithm(len));
+            assert_eq!(loaded.shard(&key), built.shard(&key));
+            assert!(loaded.shard(&key) < 97);
+        }
+        assert_eq!(loaded.which_algorithm(5000), ShardAlgorithm::Xxh3);
+
+        let gap = text.replace("start = 1025", "start = 2048");
+        assert_eq!(ShardConfig::from_toml_str(&gap), Err(ConfigError::Gap(1025..=2047)));
+        let unknown = text.replace("\"fnv1a\"", "\"md5\"");
+        assert!(matches!(
+            ShardConfig::from_toml_str(&unknown),
+            Err(ConfigError::Parse(message)) if message.contains("md5")
+        ));
+        assert!(matches!(ShardConfig::from_toml_str("[[tiers]\n"), Err(ConfigError::Parse(_))));
+
+        let path = std::env::temp_dir().join(format!("fast-shard-{}.toml", std::process::id()));
+        std::fs::write(&path, text).unwrap();
+        assert_eq!(ShardConfig::from_path(&path).map(|config| config.seed), Ok(42));
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(ShardConfig::from_path(&path), Err(ConfigError::Read(_))));
+    }
+
+    #[test]
+    fn test_finalizer_improves_lane_sum_avalanche() {
+        // Average fraction of the 64 hash bits that flip when one key bit
+        // flips; 0.5 is ideal. A weighted lane sum is linear, so a flip only
+        // carries upward within its 32-bit output.
+        let avalanche = |finalizer: Finalizer| {
+            let mut flipped = 0u64;
+            let mut trials = 0u64;
+            for seed in 0..64u64 {
+                let mut key = [0u8; 32];
+                for (i, chunk) in key.chunks_mut(8).enumerate() {
+                    chunk.copy_from_slice(&mix::splitmix64(seed * 4 + i as u64).to_le_bytes());
+                }
+                let base = finalizer.apply(plain_lane_sum(&key) as u64);
+                for bit in 0..key.len() * 8 {
+                    key[bit / 8] ^= 1 << (bit % 8);
+                    let hash = finalizer.apply(plain_lane_sum(&key) as u64);
+                    key[bit / 8] ^= 1 << (bit % 8);
+                    flipped += (base ^ hash).count_ones() as u64;
+                    trials += 64;
+                }
+            }
+            flipped as f64 / trials as f64
+        };
+
+        let raw = avalanche(Finalizer::None);
+        assert!(raw < 0.2, "{}", raw);
+        for finalizer in [Finalizer::Fmix64, Finalizer::SplitMix] {
+            let mixed = avalanche(finalizer);
+            assert!((mixed - 0.5).abs() < 0.02, "{:?}: {}", finalizer, mixed);
+        }
+
+        let mut weak = FastShard::with_hasher(1024, |key| plain_lane_sum(key) as u64);
+        weak.config.finalizer = Finalizer::Fmix64;
+        let key = b"0123456789abcdef";
+        let hash = plain_lane_sum(key) as u64;
+        assert_eq!(weak.shard(key) as u64, Finalizer::Fmix64.apply(hash) % 1024);
+        assert_eq!(FastShard::new(1024).config.finalizer, Finalizer::None);
+    }
+
+    #[test]
+    fn test_aesni_avalanche() {
+        // Average fraction of the 32 output bits that flip when one key bit
+        // flips, over one-block, exact-block and multi-block keys; 0.5 is
+        // ideal. The scalar model stands in for the kernel, which the
+        // cross-check tests tie to it.
+        for len in [4, 8, 16, 40] {
+            let mut flipped = 0u64;
+            let mut trials = 0u64;
+            for seed in 0..64u64 {
+                let mut key: Vec<u8> =
+                    (0..len as u64).map(|i| mix::splitmix64(seed * 64 + i) as u8).collect();
+                let base = reference::aesni_hash(&key);
+                for bit in 0..len * 8 {
+                    key[bit / 8] ^= 1 << (bit % 8);
+                    let hash = reference::aesni_hash(&key);
+                    key[bit / 8] ^= 1 << (bit % 8);
+                    flipped += (base ^ hash).count_ones() as u64;
+                    trials += 32;
+                }
+            }
+            let avalanche = flipped as f64 / trials as f64;
+            assert!((avalanche - 0.5).abs() < 0.02, "len {}: {}", len, avalanche);
+        }
+
+        // Keys differing only past their fourth byte spread too, and zero
+        // padding does not collide with explicit zeros.
+        let shards: std::collections::HashSet<u32> =
+            (0..20_000).map(|i| reference::aesni_hash(format!("k{}", i).as_bytes()) % 1021).collect();
+        assert!(shards.len() > 1000, "{} shards hit", shards.len());
+        assert_ne!(reference::aesni_hash(b"a"), reference::aesni_hash(b"a\0"));
+        assert_ne!(reference::aesni_hash_seeded(b"a", 0), reference::aesni_hash_seeded(b"a", 286));
+    }
+
+    #[test]
+    fn test_shard_iovecs_matches_contiguous_key() {
+        let a = b"GET /objects/".to_vec();
+        let b = b"tenant-42/photo.jpg".to_vec();
+        let mut shards = vec![
+            FastShard::new(1021),
+            FastShard::with_config(1021, ShardConfig::single(fallback_algorithm())),
+        ];
+        #[cfg(feature = "fnv")]
+        shards.push(FastShard::with_config(1021, ShardConfig::single(ShardAlgorithm::Fnv1a)));
+        #[cfg(feature = "xxh3")]
+        shards.push(FastShard::with_xxh3_secret(1021, vec![7u8; 200]).unwrap());
+
+        for shard in &shards {
+            assert_eq!(shard.shard_iovecs(&[&a, &b]), shard.shard(&[a.clone(), b.clone()].concat()));
+            assert_eq!(shard.shard_iovecs(&[&a[..3], &[], &a[3..]]), shard.shard(&a));
+            assert_eq!(shard.shard_iovecs(&[]), shard.shard(b""));
+        }
+    }
+
+    #[test]
+    fn test_shard_iovecs_single_shard_is_counted() {
+        let config = ShardConfig::single(fallback_algorithm());
+        for shard in [FastShard::new(1), FastShard::with_config(1, config)] {
+            let shard = shard.with_metrics();
+            assert_eq!(shard.shard_iovecs(&[b"GET /objects/", b"tenant-42/photo.jpg"]), 0);
+            assert_eq!(shard.shard_iovecs(&[]), 0);
+            assert_eq!(shard.shard_counts(), vec![2]);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_std_default_matches_default_hasher() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        let key: &[u8] = b"migrating-from-hashmap";
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let expected = hasher.finish();
+
+        let shard = FastShard::with_config(1000, ShardConfig::single(ShardAlgorithm::StdDefault));
+        assert_eq!(shard.hash64(key), expected);
+        assert_eq!(shard.shard(key) as u64, expected % 1000);
+    }
+
+    #[test]
+    fn test_key_transform_lowercase() {
+        let shard = FastShard::new(1024).with_key_transform(|key| {
+            if key.iter().any(u8::is_ascii_uppercase) {
+                Cow::Owned(key.to_ascii_lowercase())
+            } else {
+                Cow::Borrowed(key)
+            }
+        });
+        let plain = FastShard::new(1024);
+
+        assert_eq!(shard.shard(b"ABC"), shard.shard(b"abc"));
+        assert_eq!(shard.shard(b"ABC"), plain.shard(b"abc"));
+        assert_eq!(shard.shard_pair(b"User", b"ID"), shard.shard_pair(b"user", b"id"));
+
+        let keys: [&[u8]; 3] = [b"ABC", b"Mixed-Case-Key-Longer-Than-Sixteen", b"abc"];
+        let mut out = [0u32; 3];
+        shard.shard_batch_by_tier(&keys, &mut out);
+        assert_eq!(out[0], out[2]);
+        assert_eq!(out[1], plain.shard(b"mixed-case-key-longer-than-sixteen"));
+    }
+
+    // `shard` hashes each key with exactly the algorithm `which_algorithm`
+    // reports, and that algorithm is one the host can run.
+    #[test]
+    fn test_which_algorithm_matches_shard_path() {
+        let available = FastShard::supported_algorithms();
+        let mut configs: Vec<(Option<ShardAlgorithm>, ShardConfig)> = vec![(None, ShardConfig::default())];
+        for algo in info::ALGORITHMS {
+            configs.push((Some(algo.clone()), ShardConfig::single(algo)));
+        }
+        for (preferred, config) in configs {
+            let shard = FastShard::with_config(1021, config);
+            for len in [0, 1, 8, 15, 16, 17, 31, 64, 100, 1000] {
+                let key: Vec<u8> = (0..len).map(|i| (i as u8).wrapping_mul(29) ^ 0x5a).collect();
+                let algorithm = shard.which_algorithm(len);
+                assert!(available.contains(&algorithm), "{:?}", algorithm);
+                if let Some(preferred) = &preferred {
+                    let expected = if available.contains(preferred) { preferred } else { &fallback_algorithm() };
+                    assert_eq!(&algorithm, expected);
+                }
+                let hash = shard.hash_with_algorithm(&key, algorithm.clone());
+                assert_eq!(shard.hash64(&key), hash, "{:?} len {}", algorithm, len);
+                assert_eq!(shard.shard(&key), shard.reduce(hash), "{:?} len {}", algorithm, len);
+            }
+        }
+    }
+
+    #[test]
+    fn test_shard_with_matches_single_algorithm_config() {
+        let shard = FastShard::new(1021);
+        for algo in info::ALGORITHMS {
+            let only = FastShard::with_config(1021, ShardConfig::single(algo.clone()));
+            for len in [0, 3, 8, 16, 17, 64, 200] {
+                let key: Vec<u8> = (0..len).map(|i| (i as u8).wrapping_mul(13) ^ len as u8).collect();
+                assert_eq!(shard.shard_with(&key, algo.clone()), only.shard(&key), "{:?} len {}", algo, len);
+            }
+        }
+
+        // Options other than the tiers still apply; a custom hasher does not.
+        let options = ShardConfig {
+            seed: 279,
+            min_key_len: Some(8),
+            ..ShardConfig::single(fallback_algorithm())
+        };
+        let seeded = FastShard::with_config(64, ShardConfig { tiers: Vec::new(), ..options.clone() }).with_metrics();
+        let seeded_only = FastShard::with_config(64, options);
+        assert_eq!(seeded.shard_with(b"abc", fallback_algorithm()), seeded_only.shard(b"abc"));
+        assert_eq!(seeded.shard_counts().iter().sum::<u64>(), 1);
+        let hashed = FastShard::with_hasher(64, |_| 0);
+        assert_eq!(hashed.shard(b"abc"), 0);
+        assert_eq!(
+            hashed.shard_with(b"abc", fallback_algorithm()),
+            FastShard::with_config(64, ShardConfig::single(fallback_algorithm())).shard(b"abc")
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "xxh3")]
+    fn test_shard_with_xxh3_matches_deterministic_config() {
+        let shard = FastShard::new(1000);
+        let deterministic = FastShard::with_config(1000, ShardConfig::deterministic());
+        for i in 0..1000u32 {
+            let key = format!("key-{}", i);
+            assert_eq!(shard.shard_with(key.as_bytes(), ShardAlgorithm::Xxh3), deterministic.shard(key.as_bytes()));
+        }
+    }
+
+    #[test]
+    fn test_default_small_key_boundary() {
+        let config = ShardConfig::default();
+        assert_eq!(*config.tiers[0].size_range.end(), DEFAULT_SMALL_KEY_MAX);
+        assert_eq!(*config.tiers[1].size_range.start(), DEFAULT_SMALL_KEY_MAX + 1);
+    }
+
+    #[test]
+    fn test_default_config() {
+        let shard = FastShard::new(16);
+        
+        // Test various key sizes
+        let keys = vec![
             vec![0u8; 8],    // Small
             vec![0u8; 16],   // Border
             vec![0u8; 32],   // Medium
@@ -266,5 +3111,21 @@ mod tests {
             let _ = shard.shard(&key);
         }
     }
-}
 
+    // Run by `cargo test --no-default-features --features xxh3`: the core
+    // path must place keys exactly as the std build does.
+    #[test]
+    #[cfg(all(not(feature = "std"), feature = "xxh3"))]
+    fn test_no_std_shard_path() {
+        let shard = FastShard::with_config(1000, ShardConfig::deterministic());
+        let keys: Vec<Vec<u8>> = (0..100u64).map(|i| i.to_le_bytes().to_vec()).collect();
+        for key in &keys {
+            assert_eq!(shard.hash64(key), xxhash_rust::xxh3::xxh3_64(key));
+            assert_eq!(shard.shard(key) as u64, xxhash_rust::xxh3::xxh3_64(key) % 1000);
+        }
+
+        let refs: Vec<&[u8]> = keys.iter().map(Vec::as_slice).collect();
+        let batch = shard.shard_batch_vec(&refs);
+        assert!(batch.iter().zip(&keys).all(|(&s, key)| s == shard.shard(key)));
+    }
+}
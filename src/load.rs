@@ -0,0 +1,91 @@
+// File: src/load.rs
+//
+// Reading a `ShardConfig` from an ops-edited file. The text is deserialized
+// with serde and then run through `ShardConfig::validate`, so a bad file
+// fails here rather than at the first `shard` call.
+//
+// TOML integers stop at `i64::MAX`, so an open-ended tier cannot spell out
+// `usize::MAX`; `size_range` therefore omits its `end` for that bound and
+// reads a missing `end` back as it (the `size_range` module below).
+
+#[cfg(feature = "serde")]
+pub(crate) mod size_range {
+    use core::ops::RangeInclusive;
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct Bounds {
+        start: usize,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        end: Option<usize>,
+    }
+
+    pub(crate) fn serialize<S: Serializer>(range: &RangeInclusive<usize>, serializer: S) -> Result<S::Ok, S::Error> {
+        let end = *range.end();
+        Bounds {
+            start: *range.start(),
+            end: (end != usize::MAX).then_some(end),
+        }
+        .serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<RangeInclusive<usize>, D::Error> {
+        let bounds = Bounds::deserialize(deserializer)?;
+        Ok(bounds.start..=bounds.end.unwrap_or(usize::MAX))
+    }
+}
+
+#[cfg(any(feature = "toml", feature = "json"))]
+mod file {
+    use std::path::Path;
+
+    use crate::{ConfigError, ShardConfig};
+
+    impl ShardConfig {
+        /// Parses and validates a TOML config. Tiers are an array of tables
+        /// (`[[tiers]]` with `size_range = { start = 0, end = 64 }` and
+        /// `algorithms = ["avx2", "xxh3"]`); leave out `end` for a tier that
+        /// runs to `usize::MAX`. Fields not given take their default values.
+        ///
+        /// Fails with [`ConfigError::Parse`] on malformed TOML, unknown
+        /// algorithm names or fields of the wrong type, and otherwise with
+        /// whatever [`ShardConfig::validate`] reports.
+        #[cfg(feature = "toml")]
+        pub fn from_toml_str(text: &str) -> Result<Self, ConfigError> {
+            let config: ShardConfig = toml::from_str(text).map_err(|err| ConfigError::Parse(err.to_string()))?;
+            config.validate()?;
+            Ok(config)
+        }
+
+        /// Like [`ShardConfig::from_toml_str`], for the JSON form the
+        /// `serde` feature documents.
+        #[cfg(feature = "json")]
+        pub fn from_json_str(text: &str) -> Result<Self, ConfigError> {
+            let config: ShardConfig = serde_json::from_str(text).map_err(|err| ConfigError::Parse(err.to_string()))?;
+            config.validate()?;
+            Ok(config)
+        }
+
+        /// Reads the file at `path` and parses it as TOML or JSON according
+        /// to its `.toml` or `.json` extension. Fails with
+        /// [`ConfigError::Read`] if the file cannot be read or has another
+        /// extension (or one whose feature is off).
+        pub fn from_path(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+            let path = path.as_ref();
+            let read = || {
+                std::fs::read_to_string(path).map_err(|err| ConfigError::Read(format!("{}: {}", path.display(), err)))
+            };
+            match path.extension().and_then(|ext| ext.to_str()) {
+                #[cfg(feature = "toml")]
+                Some("toml") => Self::from_toml_str(&read()?),
+                #[cfg(feature = "json")]
+                Some("json") => Self::from_json_str(&read()?),
+                _ => Err(ConfigError::Read(format!(
+                    "{}: unsupported config file extension",
+                    path.display()
+                ))),
+            }
+        }
+    }
+}
@@ -0,0 +1,181 @@
+// File: src/maglev.rs
+//
+// Maglev consistent hashing (Eisenbud et al., "Maglev: A Fast and Reliable
+// Software Network Load Balancer", section 3.4). Every shard walks its own
+// permutation of a prime-sized table, and the shards take turns claiming
+// their next free slot until the table is full. Each shard ends up with
+// `table_size / shard_count` slots give or take one, and dropping a shard
+// mostly hands its slots to others while leaving theirs in place.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::mix::splitmix64;
+use crate::FastShard;
+
+// Separate the offset and skip streams drawn from one shard id's hash.
+const OFFSET_SALT: u64 = 0x243F_6A88_85A3_08D3;
+const SKIP_SALT: u64 = 0x1319_8A2E_0370_7344;
+
+/// A precomputed Maglev lookup table over a [`FastShard`]'s key hashes:
+/// [`lookup`](MaglevShard::lookup) is one hash and one table read. Rebuild
+/// it whenever the set of shards changes.
+#[derive(Debug, Clone)]
+pub struct MaglevShard<'a> {
+    shard: &'a FastShard,
+    table: Vec<u32>,
+}
+
+impl<'a> MaglevShard<'a> {
+    /// A table over shards `0..shard_count` of `shard`; see
+    /// [`MaglevShard::build_for`].
+    pub fn build(shard: &'a FastShard, table_size: usize) -> Self {
+        Self::build_for(shard, 0..shard.shard_count(), table_size)
+    }
+
+    /// A table over the shard ids in `shards`, e.g. every id but a failed
+    /// one. Each id's permutation comes from `shard`'s hash of its
+    /// little-endian bytes, so an id claims the same slots whatever else is
+    /// in the set. `table_size` should be well above the number of shards
+    /// (100 times gives about 1% imbalance).
+    ///
+    /// # Panics
+    ///
+    /// If `table_size` is not prime, is smaller than the number of shards,
+    /// or `shards` is empty.
+    pub fn build_for(shard: &'a FastShard, shards: impl IntoIterator<Item = u32>, table_size: usize) -> Self {
+        let shards: Vec<u32> = shards.into_iter().collect();
+        assert!(!shards.is_empty(), "a Maglev table needs at least one shard");
+        assert!(is_prime(table_size), "Maglev table size {} is not prime", table_size);
+        assert!(
+            table_size >= shards.len(),
+            "Maglev table size {} is below the shard count",
+            table_size
+        );
+
+        let size = table_size as u64;
+        // Each shard's permutation is `offset + j * skip` (mod size); a
+        // prime size makes every nonzero skip visit every slot. Both come
+        // from independently mixed copies of the id's hash, since FNV-1a on
+        // consecutive ids differs only in the low bits.
+        let permutations: Vec<(u64, u64)> = shards
+            .iter()
+            .map(|id| {
+                let hash = shard.hash64(&id.to_le_bytes());
+                (
+                    splitmix64(hash ^ OFFSET_SALT) % size,
+                    splitmix64(hash ^ SKIP_SALT) % (size - 1) + 1,
+                )
+            })
+            .collect();
+        let mut next = vec![0u64; shards.len()];
+        // Every id is a valid shard, so free slots are tracked separately.
+        let mut taken = vec![false; table_size];
+        let mut table = vec![0u32; table_size];
+        let mut filled = 0;
+        'fill: loop {
+            for (i, &(offset, skip)) in permutations.iter().enumerate() {
+                let mut slot = ((offset + next[i] * skip) % size) as usize;
+                while taken[slot] {
+                    next[i] += 1;
+                    slot = ((offset + next[i] * skip) % size) as usize;
+                }
+                taken[slot] = true;
+                table[slot] = shards[i];
+                next[i] += 1;
+                filled += 1;
+                if filled == table_size {
+                    break 'fill;
+                }
+            }
+        }
+        Self { shard, table }
+    }
+
+    /// The shard for `key`.
+    pub fn lookup(&self, key: &[u8]) -> u32 {
+        self.table[(splitmix64(self.shard.hash64(key)) % self.table.len() as u64) as usize]
+    }
+
+    /// The table itself: slot `i` holds the shard for keys whose mixed
+    /// hash, `splitmix64(hash64(key))`, is congruent to `i`.
+    pub fn table(&self) -> &[u32] {
+        &self.table
+    }
+}
+
+fn is_prime(n: usize) -> bool {
+    n >= 2 && (2..).take_while(|d| d * d <= n).all(|d| !n.is_multiple_of(d))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_maglev_table_is_balanced() {
+        let shard = FastShard::new(50);
+        let maglev = MaglevShard::build(&shard, 65_537);
+        let mut slots = [0usize; 50];
+        for &id in maglev.table() {
+            slots[id as usize] += 1;
+        }
+        let fair = 65_537 / 50;
+        assert!(slots.iter().all(|&n| n == fair || n == fair + 1), "{:?}", slots);
+    }
+
+    #[test]
+    fn test_maglev_removal_moves_about_one_in_n() {
+        assert_removal_moves_about_one_in_n(&FastShard::new(50));
+        // FNV-1a hashes of consecutive ids share their high bits.
+        #[cfg(feature = "fnv")]
+        assert_removal_moves_about_one_in_n(&FastShard::with_config(
+            50,
+            crate::ShardConfig::single(crate::ShardAlgorithm::Fnv1a),
+        ));
+    }
+
+    fn assert_removal_moves_about_one_in_n(shard: &FastShard) {
+        let before = MaglevShard::build(shard, 65_537);
+        let after = MaglevShard::build_for(shard, (0..50).filter(|&id| id != 17), 65_537);
+        let keys = 100_000u32;
+        let (mut moved, mut moved_elsewhere) = (0, 0);
+        for i in 0..keys {
+            let key = format!("key-{}", i);
+            let old = before.lookup(key.as_bytes());
+            let new = after.lookup(key.as_bytes());
+            assert_ne!(new, 17);
+            if old != new {
+                moved += 1;
+                if old != 17 {
+                    moved_elsewhere += 1;
+                }
+            }
+        }
+        let fraction = moved as f64 / keys as f64;
+        assert!((fraction - 1.0 / 50.0).abs() < 0.005, "moved {}", fraction);
+        // Keys on surviving shards almost all stay.
+        assert!(
+            (moved_elsewhere as f64) < keys as f64 * 0.005,
+            "{} moved between survivors",
+            moved_elsewhere
+        );
+    }
+
+    #[test]
+    fn test_maglev_accepts_u32_max_id() {
+        let shard = FastShard::new(4);
+        let maglev = MaglevShard::build_for(&shard, [0, 1, u32::MAX], 101);
+        let mut slots = [0usize; 3];
+        for &id in maglev.table() {
+            slots[if id == u32::MAX { 2 } else { id as usize }] += 1;
+        }
+        assert!(slots.iter().all(|&n| n == 33 || n == 34), "{:?}", slots);
+    }
+
+    #[test]
+    #[should_panic(expected = "not prime")]
+    fn test_maglev_rejects_composite_size() {
+        MaglevShard::build(&FastShard::new(4), 1000);
+    }
+}
@@ -0,0 +1,324 @@
+// File: src/metrics.rs
+//
+// Opt-in per-shard hit counters for hot-shard detection, plus (with `std`) a
+// streaming sketch of per-shard load over time for `load_percentile`.
+
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "std")]
+use std::sync::Mutex;
+
+use crate::FastShard;
+
+// Placements per shard between ticks of the window clock.
+#[cfg(feature = "std")]
+const WINDOW_TICK: u64 = 64;
+
+// Relative accuracy of `LoadSketch` estimates.
+#[cfg(feature = "std")]
+const SKETCH_ALPHA: f64 = 0.01;
+
+// One counter per cache line, so threads bumping neighbouring shards do not
+// contend for the same line. `window` counts since the current load window
+// opened.
+#[repr(align(64))]
+struct Counter {
+    total: AtomicU64,
+    #[cfg(feature = "std")]
+    window: AtomicU64,
+}
+
+pub(crate) struct ShardMetrics {
+    counts: Box<[Counter]>,
+    // A window closes every `shard_count` ticks, where a shard ticks on
+    // every `WINDOW_TICK`-th placement, so about `WINDOW_TICK` keys per
+    // shard without a counter shared by every placement.
+    #[cfg(feature = "std")]
+    ticks: AtomicU64,
+    #[cfg(feature = "std")]
+    sketch: Mutex<LoadSketch>,
+}
+
+impl ShardMetrics {
+    pub(crate) fn new(shard_count: u32) -> Self {
+        Self {
+            counts: (0..shard_count)
+                .map(|_| Counter {
+                    total: AtomicU64::new(0),
+                    #[cfg(feature = "std")]
+                    window: AtomicU64::new(0),
+                })
+                .collect(),
+            #[cfg(feature = "std")]
+            ticks: AtomicU64::new(0),
+            #[cfg(feature = "std")]
+            sketch: Mutex::new(LoadSketch::new()),
+        }
+    }
+
+    pub(crate) fn record(&self, shard: u32) {
+        let counter = &self.counts[shard as usize];
+        let total = counter.total.fetch_add(1, Ordering::Relaxed) + 1;
+        #[cfg(feature = "std")]
+        {
+            counter.window.fetch_add(1, Ordering::Relaxed);
+            if total.is_multiple_of(WINDOW_TICK) {
+                let ticks = self.ticks.fetch_add(1, Ordering::Relaxed) + 1;
+                if ticks.is_multiple_of(self.counts.len() as u64) {
+                    self.close_window();
+                }
+            }
+        }
+        #[cfg(not(feature = "std"))]
+        let _ = total;
+    }
+
+    // Feeds every shard's load in the window into the sketch and opens the
+    // next one. Placements racing with it land in either window.
+    #[cfg(feature = "std")]
+    fn close_window(&self) {
+        let mut sketch = self.sketch.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        for counter in self.counts.iter() {
+            sketch.insert(counter.window.swap(0, Ordering::Relaxed));
+        }
+    }
+}
+
+// Log-bucketed quantile sketch (DDSketch, Masson et al., VLDB 2019): a value
+// `v > 0` is counted in bucket `ceil(log_gamma(v))`, so each estimate is
+// within `SKETCH_ALPHA` of a true value, in space logarithmic in the largest
+// value seen.
+#[cfg(feature = "std")]
+struct LoadSketch {
+    zeros: u64,
+    buckets: Vec<u64>,
+    len: u64,
+}
+
+#[cfg(feature = "std")]
+impl LoadSketch {
+    fn new() -> Self {
+        Self {
+            zeros: 0,
+            buckets: Vec::new(),
+            len: 0,
+        }
+    }
+
+    fn ln_gamma() -> f64 {
+        ((1.0 + SKETCH_ALPHA) / (1.0 - SKETCH_ALPHA)).ln()
+    }
+
+    fn insert(&mut self, value: u64) {
+        self.len += 1;
+        if value == 0 {
+            self.zeros += 1;
+            return;
+        }
+        let bucket = ((value as f64).ln() / Self::ln_gamma()).ceil() as usize;
+        if bucket >= self.buckets.len() {
+            self.buckets.resize(bucket + 1, 0);
+        }
+        self.buckets[bucket] += 1;
+    }
+
+    // Nearest-rank quantile, `q` in `0.0..=1.0`; 0 when empty.
+    fn quantile(&self, q: f64) -> u64 {
+        let rank = ((q.clamp(0.0, 1.0) * self.len as f64).ceil() as u64).max(1);
+        let mut seen = self.zeros;
+        if rank <= seen {
+            return 0;
+        }
+        for (bucket, &count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                let gamma = Self::ln_gamma().exp();
+                return (2.0 * (bucket as f64 * Self::ln_gamma()).exp() / (gamma + 1.0)).round() as u64;
+            }
+        }
+        0
+    }
+
+    fn clear(&mut self) {
+        *self = Self::new();
+    }
+}
+
+impl FastShard {
+    /// Counts, per shard, how many keys landed there. Every call that places
+    /// keys with this instance's mapping counts once per key: `shard` and its
+    /// variants (`shard16`, `try_shard`, `shard_u64`, `shard_u128`,
+    /// `shard_with`, `hash_and_shard`, `shard_iovecs`, `shard_with_key_fn`,
+    /// [`ShardHasher::finish`](crate::ShardHasher::finish)), the batch calls,
+    /// `shard_records`, `shard_skip_prefix`, `shard_pair`,
+    /// `shard_with_prefix`, `shard_of_u32`, `shard_token` and
+    /// `CollisionDetector::shard` on a wrapped instance. Not counted are
+    /// [`shard64`](Self::shard64), which maps onto a different shard space,
+    /// the jump, rendezvous and Maglev placements, and the analysis helpers.
+    ///
+    /// The counters are atomic and each fills a 64-byte cache line, so
+    /// concurrent callers do not slow each other down through false sharing;
+    /// that is 64 bytes of memory per shard. With `std` the counts also feed
+    /// [`load_percentile`](Self::load_percentile).
+    pub fn with_metrics(mut self) -> Self {
+        self.metrics = Some(Box::new(ShardMetrics::new(self.shard_count)));
+        self
+    }
+
+    /// Hits per shard since construction, indexed by shard id; all zeros
+    /// unless built [`with_metrics`](Self::with_metrics).
+    pub fn shard_counts(&self) -> Vec<u64> {
+        match &self.metrics {
+            Some(metrics) => metrics.counts.iter().map(|c| c.total.load(Ordering::Relaxed)).collect(),
+            None => vec![0; self.shard_count as usize],
+        }
+    }
+
+    /// Zeroes every counter and forgets the load history behind
+    /// [`load_percentile`](Self::load_percentile). Calls racing with the
+    /// reset may land on either side of it.
+    pub fn reset_metrics(&self) {
+        if let Some(metrics) = &self.metrics {
+            for counter in metrics.counts.iter() {
+                counter.total.store(0, Ordering::Relaxed);
+                #[cfg(feature = "std")]
+                counter.window.store(0, Ordering::Relaxed);
+            }
+            #[cfg(feature = "std")]
+            {
+                metrics.ticks.store(0, Ordering::Relaxed);
+                metrics
+                    .sketch
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .clear();
+            }
+        }
+    }
+
+    /// The `p`-th percentile (`0.0..=100.0`) of per-shard load over time.
+    /// Metrics mode cuts the placements into windows of about 64 keys per
+    /// shard, and as each window closes it feeds every shard's count in that
+    /// window into a streaming quantile sketch (DDSketch, within 1% of the
+    /// true value). `load_percentile(99.0)` is thus the load a shard exceeds
+    /// in only 1% of its windows, so a shard that runs hot now and then
+    /// shows in the tail even when its total since construction looks
+    /// ordinary. A p99 far above the median points at hot shards. Returns 0
+    /// until the first window closes, and without
+    /// [`with_metrics`](Self::with_metrics). Requires the `std` feature.
+    #[cfg(feature = "std")]
+    pub fn load_percentile(&self, p: f64) -> u64 {
+        match &self.metrics {
+            Some(metrics) => {
+                let sketch = metrics.sketch.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                sketch.quantile(p / 100.0)
+            }
+            None => 0,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::{fallback_algorithm, ShardConfig};
+
+    #[test]
+    fn test_load_percentile_surfaces_hot_shards() {
+        let config = ShardConfig::single(fallback_algorithm());
+        let shard = FastShard::with_config(200, config).with_metrics();
+        assert_eq!(shard.load_percentile(99.0), 0);
+
+        // Half the traffic is a uniform background, half a few hot keys.
+        for i in 0..100_000u64 {
+            let key = if i % 2 == 0 { crate::mix::splitmix64(i) } else { i % 10 };
+            shard.shard(&key.to_le_bytes());
+        }
+
+        assert_eq!(shard.shard_counts().iter().sum::<u64>(), 100_000);
+        let median = shard.load_percentile(50.0);
+        let p99 = shard.load_percentile(99.0);
+        assert!(median > 0 && p99 > 10 * median, "p99 {} vs median {}", p99, median);
+
+        shard.reset_metrics();
+        assert_eq!(shard.load_percentile(99.0), 0);
+        assert_eq!(FastShard::new(8).load_percentile(99.0), 0);
+    }
+
+    #[test]
+    fn test_self_check_and_empty_shards_do_not_count() {
+        let shard = FastShard::new(64).with_metrics();
+        shard.self_check().unwrap();
+        assert_eq!(shard.empty_shards((0..1000u32).map(|i| i.to_le_bytes())), 0);
+        assert!(shard.shard_counts().iter().all(|&c| c == 0));
+
+        let single = FastShard::new(1).with_metrics();
+        assert_eq!(single.empty_shards([b"key"]), 0);
+        assert_eq!(single.empty_shards(core::iter::empty::<&[u8]>()), 1);
+        assert_eq!(single.shard_counts(), [0]);
+    }
+
+    #[test]
+    fn test_load_sketch_is_within_alpha() {
+        let mut sketch = LoadSketch::new();
+        assert_eq!(sketch.quantile(0.5), 0);
+        for value in 0..=1000u64 {
+            sketch.insert(value);
+        }
+        for (q, exact) in [(0.0, 0u64), (0.25, 250), (0.5, 500), (0.99, 990), (1.0, 1000)] {
+            let estimate = sketch.quantile(q) as f64;
+            assert!(
+                (estimate - exact as f64).abs() <= exact as f64 * SKETCH_ALPHA + 1.0,
+                "{} at {}",
+                estimate,
+                q
+            );
+        }
+    }
+
+    #[test]
+    fn test_counts_add_up_across_threads() {
+        let shard = FastShard::new(1000).with_metrics();
+        let (threads, calls) = (8u64, 20_000u64);
+        std::thread::scope(|scope| {
+            for t in 0..threads {
+                let shard = &shard;
+                scope.spawn(move || {
+                    for i in 0..calls {
+                        shard.shard(&(t * calls + i).to_le_bytes());
+                    }
+                });
+            }
+        });
+        let counts = shard.shard_counts();
+        assert_eq!(counts.len(), 1000);
+        assert_eq!(counts.iter().sum::<u64>(), threads * calls);
+
+        shard.reset_metrics();
+        assert!(shard.shard_counts().iter().all(|&c| c == 0));
+        shard.shard(b"key");
+        assert_eq!(shard.shard_counts().iter().sum::<u64>(), 1);
+        assert_eq!(core::mem::align_of::<Counter>(), 64);
+    }
+
+    #[test]
+    fn test_every_placing_call_counts() {
+        let shard = FastShard::new(64).with_metrics();
+        let keys: [&[u8]; 3] = [b"user:42", b"a key longer than the small-key tier", &[7; 300]];
+        let mut out = [0u32; 3];
+        shard.shard_batch_by_tier(&keys, &mut out);
+        shard.shard_records(&[1, 2, 3, 4, 5, 6], 3, 0..2, &mut out[..2]);
+        shard.shard_skip_prefix(b"tenant:user:42", 7);
+        shard.shard_pair(b"tenant", b"user:42");
+        shard.shard_with_prefix(b"tenant", b"user:42");
+        shard.shard_of_u32(0xdead_beef);
+        shard.shard_token(shard.tokenize(b"user:42"));
+        assert_eq!(shard.shard_counts().iter().sum::<u64>(), 10);
+
+        // A different shard space, so nothing to count.
+        shard.shard64(b"user:42", 1 << 40);
+        assert_eq!(shard.shard_counts().iter().sum::<u64>(), 10);
+    }
+}
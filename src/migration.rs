@@ -0,0 +1,90 @@
+// File: src/migration.rs
+//
+// Planning for shard count changes: which keys a new count moves under the
+// instance's `shard` placement.
+
+use alloc::vec::Vec;
+
+use crate::FastShard;
+
+impl FastShard {
+    /// The keys whose [`shard`](FastShard::shard) placement changes when the
+    /// shard count goes to `new_count`, as `(key_index, old_shard,
+    /// new_shard)` in key order. Keys that stay put are left out. The new
+    /// placement comes from a copy of this instance with `new_count` shards
+    /// and the same config, secret and key hooks; metrics are not recorded.
+    /// `with_weights` weights are not carried over: the new count is
+    /// planned with the unweighted mapping.
+    ///
+    /// Under the default modulo mapping almost every key moves; compare
+    /// [`jump_shard`](FastShard::jump_shard) and
+    /// [`rendezvous_shard`](FastShard::rendezvous_shard), which move about
+    /// `1 / new_count` of them when growing by one shard.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `new_count` is zero.
+    pub fn migration_plan(&self, keys: &[&[u8]], new_count: u32) -> Vec<(usize, u32, u32)> {
+        assert!(new_count > 0, "new_count must be nonzero");
+        let resized = self.resized(new_count);
+        keys.iter()
+            .enumerate()
+            .filter_map(|(index, key)| {
+                // The hash does not depend on the shard count; only the
+                // mapping does.
+                let hash = self.hash64(key);
+                let (old, new) = (self.reduce(hash), resized.reduce(hash));
+                (old != new).then_some((index, old, new))
+            })
+            .collect()
+    }
+
+    // This instance's placement with `shard_count` shards, without the
+    // per-instance extras (metrics, caches) that do not affect it.
+    fn resized(&self, shard_count: u32) -> FastShard {
+        let mut resized = FastShard::with_config(shard_count, self.config.clone());
+        #[cfg(feature = "xxh3")]
+        {
+            resized.xxh3_secret = self.xxh3_secret.clone();
+        }
+        resized.custom_hasher = self.custom_hasher.clone();
+        resized.key_transform = self.key_transform.clone();
+        resized
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migration_plan_modulo_vs_jump() {
+        let keys: Vec<String> = (0..10_000).map(|i| format!("key-{}", i)).collect();
+        let keys: Vec<&[u8]> = keys.iter().map(|key| key.as_bytes()).collect();
+        let (before, after) = (FastShard::new(100), FastShard::new(101));
+
+        let plan = before.migration_plan(&keys, 101);
+        let mut listed = vec![false; keys.len()];
+        for &(index, old, new) in &plan {
+            assert_eq!(old, before.shard(keys[index]));
+            assert_eq!(new, after.shard(keys[index]));
+            assert_ne!(old, new);
+            listed[index] = true;
+        }
+        for (index, key) in keys.iter().enumerate() {
+            assert_eq!(listed[index], before.shard(key) != after.shard(key));
+        }
+        assert!(plan.windows(2).all(|w| w[0].0 < w[1].0));
+
+        let jump_moved = keys
+            .iter()
+            .filter(|key| before.jump_shard(key) != after.jump_shard(key))
+            .count();
+        // Modulo keeps a key only when its hash agrees mod 100 and mod 101,
+        // about 1 in 101; jump moves only the new shard's ~1/101.
+        assert!(plan.len() > 9_800, "modulo moved {}", plan.len());
+        assert!(jump_moved > 50 && jump_moved < 150, "jump moved {}", jump_moved);
+
+        assert!(before.migration_plan(&keys, 100).is_empty());
+    }
+}
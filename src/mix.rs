@@ -0,0 +1,71 @@
+// File: src/mix.rs
+//
+// Integer mixing functions shared by the placement strategies built on top
+// of the raw 64-bit key hash.
+
+/// SplitMix64 finalizer: a cheap bijection with full avalanche.
+pub(crate) fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+/// Order-sensitive combination of two 64-bit hashes: `combine(a, b)` and
+/// `combine(b, a)` differ, unlike XOR.
+pub(crate) fn combine(a: u64, b: u64) -> u64 {
+    splitmix64(a.wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ splitmix64(b))
+}
+
+/// Optional mixing step applied to a key's 64-bit hash before it is mapped
+/// onto a shard. Weak hashes (a custom hasher that sums lanes, say) leave
+/// most output bits untouched by a single input bit; a finalizer spreads
+/// every input bit over the whole hash.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Finalizer {
+    /// Map the algorithm's hash as is.
+    #[default]
+    None,
+    /// MurmurHash3's `fmix64`.
+    Fmix64,
+    /// The SplitMix64 output function.
+    SplitMix,
+}
+
+impl Finalizer {
+    pub fn apply(self, hash: u64) -> u64 {
+        match self {
+            Finalizer::None => hash,
+            Finalizer::Fmix64 => fmix64(hash),
+            Finalizer::SplitMix => splitmix64(hash),
+        }
+    }
+}
+
+/// Avalanche applied to every SIMD kernel's 32-bit hash before it is
+/// mapped onto a shard. The lane arithmetic leaves the low bits, all that a
+/// power-of-two modulo keeps, depending on few key bits; this spreads every
+/// bit over the whole 64-bit hash, which also gives `Multiply` reduction
+/// high bits to read. It cannot separate keys the kernel already collided.
+#[cfg_attr(
+    not(any(
+        feature = "std",
+        all(target_arch = "x86_64", any(feature = "avx512", feature = "avx2", feature = "aesni", feature = "crc32c")),
+        all(target_arch = "aarch64", feature = "neon"),
+        all(target_arch = "wasm32", target_feature = "simd128", feature = "simd128")
+    )),
+    allow(dead_code)
+)]
+#[inline(always)]
+pub(crate) fn finalize(hash: u64) -> u64 {
+    fmix64(hash)
+}
+
+/// MurmurHash3's 64-bit finalizer; a bijection that maps 0 to 0.
+pub(crate) fn fmix64(mut x: u64) -> u64 {
+    x = (x ^ (x >> 33)).wrapping_mul(0xFF51_AFD7_ED55_8CCD);
+    x = (x ^ (x >> 33)).wrapping_mul(0xC4CE_B9FE_1A85_EC53);
+    x ^ (x >> 33)
+}
@@ -0,0 +1,62 @@
+// File: src/neon.rs
+//
+// AArch64 kernel built on the ARMv8 AES instructions. AESE XORs its round
+// key in before SubBytes and ShiftRows, and AESMC is MixColumns, so
+// `AESMC(AESE(state, data)) ^ key` is exactly x86's `AESENC(state ^ data,
+// key)`, and `AESE(state, 0) ^ key` is `AESENCLAST(state, key)`. The kernel
+// therefore computes the AES-NI hash bit for bit (its scalar model is
+// `reference::aesni_hash_seeded`), and a config listing `Neon` and `AesNi`
+// places keys the same on ARM and x86 hosts that run either kernel.
+//
+// As in `simd.rs`, the kernel is compiled whenever the `neon` feature is on
+// and `FastShard` calls it only when `CpuFeatures` reports the AES
+// extension.
+#![allow(dead_code)]
+
+use core::arch::aarch64::*;
+
+#[target_feature(enable = "neon,aes")]
+pub(crate) unsafe fn neon_hash(key: &[u8]) -> u32 {
+    neon_hash_in(key, 0, key.len(), &mut [0u8; 64])
+}
+
+#[target_feature(enable = "neon,aes")]
+pub(crate) unsafe fn neon_hash_seeded(key: &[u8], seed: u64) -> u32 {
+    neon_hash_in(key, seed, key.len(), &mut [0u8; 64])
+}
+
+/// `neon_hash_seeded` padding its tail chunk in `scratch`, which must be all
+/// zero and is left all zero, and XORing `folded_len` in place of the key's
+/// length.
+#[target_feature(enable = "neon,aes")]
+pub(crate) unsafe fn neon_hash_in(key: &[u8], seed: u64, folded_len: usize, scratch: &mut [u8; 64]) -> u32 {
+    let [initial, k1, k2] = crate::reference::aesni_keys(seed);
+    let mut hash = vld1q_u8(initial.as_ptr());
+    let k1 = vld1q_u8(k1.as_ptr());
+    let k2 = vld1q_u8(k2.as_ptr());
+    let zero = vdupq_n_u8(0);
+    for chunk in key.chunks(16) {
+        let data = if chunk.len() == 16 {
+            vld1q_u8(chunk.as_ptr())
+        } else {
+            debug_assert!(chunk.len() <= 16);
+            scratch[..chunk.len()].copy_from_slice(chunk);
+            let data = vld1q_u8(scratch.as_ptr());
+            scratch[..chunk.len()].fill(0);
+            data
+        };
+
+        // AESE's own key XOR absorbs the chunk.
+        hash = veorq_u8(vaesmcq_u8(vaeseq_u8(hash, data)), k1);
+        hash = veorq_u8(vaesmcq_u8(vaeseq_u8(hash, zero)), k2);
+    }
+    let mut len = [0u8; 16];
+    len[..8].copy_from_slice(&(folded_len as u64).to_le_bytes());
+    hash = veorq_u8(vaesmcq_u8(vaeseq_u8(hash, vld1q_u8(len.as_ptr()))), k1);
+    hash = veorq_u8(vaeseq_u8(hash, zero), k2);
+
+    // Stored as bytes so the result reads little-endian like `_mm_cvtsi128_si32`.
+    let mut out = [0u8; 16];
+    vst1q_u8(out.as_mut_ptr(), hash);
+    u32::from_le_bytes([out[0], out[1], out[2], out[3]])
+}
@@ -0,0 +1,98 @@
+// File: src/normalize.rs
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
+
+/// Declarative key normalization applied before hashing (and before any
+/// [`FastShard::with_key_transform`] closure), so keys that normalize to the
+/// same bytes always co-locate. Unlike a closure it is plain data, so it
+/// travels with the [`ShardConfig`]. All steps are ASCII-only; other bytes
+/// pass through untouched.
+///
+/// [`FastShard::with_key_transform`]: crate::FastShard::with_key_transform
+/// [`ShardConfig`]: crate::ShardConfig
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NormalizationPolicy {
+    /// Map `A-Z` to `a-z`.
+    pub lowercase: bool,
+    /// Drop leading and trailing ASCII whitespace.
+    pub trim: bool,
+    /// Replace each run of ASCII whitespace with a single space.
+    pub collapse_whitespace: bool,
+}
+
+impl NormalizationPolicy {
+    pub fn is_identity(&self) -> bool {
+        !(self.lowercase || self.trim || self.collapse_whitespace)
+    }
+
+    /// Normalizes `key`, borrowing it when no byte changes.
+    pub fn apply<'a>(&self, key: &'a [u8]) -> Cow<'a, [u8]> {
+        let key = if self.trim { key.trim_ascii() } else { key };
+        let lowers = self.lowercase && key.iter().any(u8::is_ascii_uppercase);
+        // Already collapsed when every whitespace byte is a lone space.
+        let collapses = self.collapse_whitespace
+            && key.iter().enumerate().any(|(i, &byte)| {
+                byte.is_ascii_whitespace()
+                    && (byte != b' ' || key.get(i + 1).is_some_and(u8::is_ascii_whitespace))
+            });
+        if !lowers && !collapses {
+            return Cow::Borrowed(key);
+        }
+
+        let mut out = Vec::with_capacity(key.len());
+        let mut in_space = false;
+        for &byte in key {
+            if self.collapse_whitespace && byte.is_ascii_whitespace() {
+                if !in_space {
+                    out.push(b' ');
+                }
+                in_space = true;
+                continue;
+            }
+            in_space = false;
+            out.push(if self.lowercase { byte.to_ascii_lowercase() } else { byte });
+        }
+        Cow::Owned(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FastShard, ShardConfig};
+
+    #[test]
+    fn test_trim_lowercase_co_locates() {
+        let config = ShardConfig {
+            normalization: NormalizationPolicy {
+                lowercase: true,
+                trim: true,
+                ..NormalizationPolicy::default()
+            },
+            ..ShardConfig::default()
+        };
+        let shard = FastShard::with_config(1024, config);
+        assert_eq!(shard.shard(b"  Foo  "), shard.shard(b"foo"));
+        assert_eq!(shard.shard(b"  Foo  "), FastShard::new(1024).shard(b"foo"));
+    }
+
+    #[test]
+    fn test_apply() {
+        let all = NormalizationPolicy {
+            lowercase: true,
+            trim: true,
+            collapse_whitespace: true,
+        };
+        assert_eq!(&*all.apply(b" \tHello \n\n World\r\n"), b"hello world");
+        assert!(matches!(all.apply(b"already normal"), Cow::Borrowed(_)));
+
+        let collapse = NormalizationPolicy {
+            collapse_whitespace: true,
+            ..NormalizationPolicy::default()
+        };
+        assert_eq!(&*collapse.apply(b"a\tb  c\n"), b"a b c ");
+        assert_eq!(&*collapse.apply(b" A b "), b" A b ");
+        assert_eq!(&*NormalizationPolicy::default().apply(b" X "), b" X ");
+    }
+}
@@ -0,0 +1,112 @@
+// File: src/observe.rs
+//
+// Opt-in callback on every placement, for telemetry that needs more than the
+// per-shard counts of `metrics.rs`: which algorithm served a key and how
+// long keys are.
+
+use alloc::sync::Arc;
+
+use crate::{FastShard, ShardAlgorithm};
+
+/// One placement, as reported to a [`FastShard::with_observer`] callback.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShardEvent {
+    /// The key's length as passed in, before any normalization or key
+    /// transform. For [`FastShard::shard_pair`] and
+    /// [`FastShard::shard_with_prefix`] it is the two parts' combined length.
+    pub key_len: usize,
+    /// The algorithm the key's size resolves to on this host (see
+    /// [`FastShard::which_algorithm`]), or the one passed to
+    /// [`FastShard::shard_with`]. After [`FastShard::shard_skip_prefix`] it
+    /// is the one the bytes past the prefix resolve to, and for a pair the
+    /// one the longer part resolves to. A [`FastShard::with_hasher`] closure
+    /// is not reflected here.
+    pub algorithm: ShardAlgorithm,
+    pub shard: u32,
+}
+
+/// A callback installed with [`FastShard::with_observer`].
+pub type ShardObserver = Arc<dyn Fn(ShardEvent) + Send + Sync>;
+
+impl FastShard {
+    /// Calls `observer` with a [`ShardEvent`] for every key placed by
+    /// `shard` and the other calls that count toward
+    /// [`with_metrics`](Self::with_metrics), batch and record calls once per
+    /// key. `shard_of_u32` and `shard_token` are the exception: they map a
+    /// hash computed elsewhere, with no key or algorithm to report, so they
+    /// are counted but not observed. It runs on the calling thread, inline,
+    /// so keep it cheap (e.g. bump counters). Without an observer the hot
+    /// path pays only a `None` check.
+    pub fn with_observer<F>(mut self, observer: F) -> Self
+    where
+        F: Fn(ShardEvent) + Send + Sync + 'static,
+    {
+        self.observer = Some(Arc::new(observer));
+        self
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::ShardConfig;
+
+    #[test]
+    fn test_observer_sees_each_placement() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&events);
+        let shard = FastShard::with_config(64, ShardConfig::default())
+            .with_observer(move |event| sink.lock().unwrap().push(event));
+
+        let keys: [&[u8]; 4] = [b"", b"user:42", b"a key longer than the small-key tier", &[7; 300]];
+        let shards: Vec<u32> = keys.iter().map(|key| shard.shard(key)).collect();
+        let forced = shard.shard_with(b"user:42", crate::fallback_algorithm());
+
+        let seen = events.lock().unwrap();
+        assert_eq!(seen.len(), keys.len() + 1);
+        for ((event, key), &placed) in seen.iter().zip(keys).zip(&shards) {
+            assert_eq!(event.key_len, key.len());
+            assert_eq!(event.algorithm, shard.which_algorithm(key.len()));
+            assert_eq!(event.shard, placed);
+        }
+        assert_eq!(seen[4].algorithm, crate::fallback_algorithm());
+        assert_eq!(seen[4].shard, forced);
+
+        // Streamed keys are reported once, at `finish`.
+        drop(seen);
+        let mut hasher = shard.hasher();
+        hasher.update(b"user:");
+        hasher.update(b"42");
+        assert_eq!(hasher.finish(), shards[1]);
+        assert_eq!(events.lock().unwrap().len(), 6);
+    }
+
+    #[test]
+    fn test_observer_sees_derived_and_batched_keys() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&events);
+        let shard = FastShard::new(64).with_observer(move |event| sink.lock().unwrap().push(event));
+
+        let keys: [&[u8]; 2] = [b"user:42", &[7; 300]];
+        let mut out = [0u32; 2];
+        shard.shard_batch_by_tier(&keys, &mut out);
+        shard.shard_records(&[7; 600], 300, 0..300, &mut out[..2]);
+        let skipped = shard.shard_skip_prefix(&[b'x'; 307], 300);
+        let pair = shard.shard_pair(b"tenant", &[7; 300]);
+        shard.shard_of_u32(0xdead_beef);
+        shard.shard_token(shard.tokenize(b"user:42"));
+        let long = out[1];
+
+        let seen = events.lock().unwrap();
+        let lens: Vec<usize> = seen.iter().map(|event| event.key_len).collect();
+        assert_eq!(lens, [7, 300, 300, 300, 307, 306]);
+        assert_eq!(seen[0].algorithm, shard.which_algorithm(7));
+        assert_eq!((seen[1].shard, seen[3].shard), (long, long));
+        assert_eq!(seen[4].algorithm, shard.which_algorithm(7));
+        assert_eq!(seen[4].shard, skipped);
+        assert_eq!(seen[5].algorithm, shard.which_algorithm(300));
+        assert_eq!(seen[5].shard, pair);
+    }
+}
@@ -0,0 +1,238 @@
+// File: src/reduce.rs
+//
+// `hash % count` without a hardware division. The divisor is fixed for the
+// life of a `FastShard`, so a reciprocal is precomputed once and each
+// reduction becomes a multiply, a subtract and at most one correction
+// (Barrett reduction). A power-of-two count skips even that and masks.
+// Either way the result is bit-identical to `%`; see the `reciprocal` and
+// `mask` entries in `benches/mapping.rs` for the speedup. The opt-in
+// `ReductionMode::Multiply` uses Lemire's multiply-shift (`fastrange` there)
+// instead.
+
+use core::marker::PhantomData;
+
+/// How a key's 64-bit hash is mapped onto `0..shard_count`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum ReductionMode {
+    /// `hash % shard_count`. The extra keys from a hash range that is not a
+    /// multiple of the count all land on the lowest shard indices.
+    #[default]
+    Modulo,
+    /// Lemire's `(hash * shard_count) >> 64`, which reads the hash's high
+    /// bits and spreads those extra keys evenly over the index range. The
+    /// total bias is the same as `Modulo` (for 64-bit hashes, negligible
+    /// either way). It reads a full-width hash; the SIMD kernels' 32-bit
+    /// hashes are finalized to 64 bits before reduction.
+    Multiply,
+}
+
+/// An integer a shard index can be returned as; the count it reduces
+/// against has the same type, so every index fits.
+pub(crate) trait ShardIndex: Copy {
+    fn widen(self) -> u64;
+    // Only called on values below a count of this type.
+    fn narrow(value: u64) -> Self;
+}
+
+macro_rules! impl_shard_index {
+    ($($ty:ty),*) => {$(
+        impl ShardIndex for $ty {
+            #[inline]
+            fn widen(self) -> u64 {
+                self as u64
+            }
+
+            #[inline]
+            fn narrow(value: u64) -> Self {
+                value as $ty
+            }
+        }
+    )*};
+}
+
+impl_shard_index!(u16, u32, u64);
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Reducer<T = u32> {
+    mode: ReductionMode,
+    count: u64,
+    // floor((2^64 - 1) / count); zero only for a zero count.
+    magic: u64,
+    // `count - 1` when `count` is a power of two.
+    mask: Option<u64>,
+    index: PhantomData<T>,
+}
+
+impl<T: ShardIndex> Reducer<T> {
+    pub(crate) fn new(count: T, mode: ReductionMode) -> Self {
+        let count = count.widen();
+        Self {
+            mode,
+            count,
+            magic: u64::MAX.checked_div(count).unwrap_or(0),
+            mask: count.is_power_of_two().then(|| count - 1),
+            index: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn reduce(&self, hash: u64) -> T {
+        T::narrow(self.reduce_wide(hash))
+    }
+
+    #[inline]
+    fn reduce_wide(&self, hash: u64) -> u64 {
+        if self.mode == ReductionMode::Multiply {
+            return ((hash as u128 * self.count as u128) >> 64) as u64;
+        }
+        if let Some(mask) = self.mask {
+            return hash & mask;
+        }
+        if self.magic == 0 {
+            // Keeps the usual division-by-zero panic for a zero count.
+            return hash % self.count;
+        }
+        // The quotient estimate is at most one short of `hash / count`.
+        let quotient = ((hash as u128 * self.magic as u128) >> 64) as u64;
+        let rem = hash - quotient * self.count;
+        if rem >= self.count {
+            rem - self.count
+        } else {
+            rem
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_barrett_matches_modulo() {
+        // Primes, including the largest below 2^32, plus a few composites.
+        let counts = [
+            1, 2, 3, 7, 1021, 65_537, 1_000_003, 4_294_967_291, 1024, 1_000_000, u32::MAX,
+        ];
+        for &count in &counts {
+            let n = count as u64;
+            let reducer = Reducer::new(n, ReductionMode::Modulo);
+            let edges = [
+                0, 1, n - 1, n, n + 1, u32::MAX as u64, u64::MAX, u64::MAX - 1, u64::MAX / n * n,
+            ];
+            for hash in edges {
+                assert_eq!(reducer.reduce(hash), hash % n, "{} % {}", hash, n);
+            }
+
+            let mut x = 0x9E37_79B9_7F4A_7C15u64 ^ n;
+            for _ in 0..100_000 {
+                x = crate::mix::splitmix64(x);
+                assert_eq!(reducer.reduce(x), x % n, "{} % {}", x, n);
+                let narrow = x >> 32;
+                assert_eq!(reducer.reduce(narrow), narrow % n, "{} % {}", narrow, n);
+            }
+        }
+    }
+
+    #[test]
+    fn test_power_of_two_mask_matches_modulo() {
+        for shift in 0..32 {
+            let count = 1u64 << shift;
+            let reducer = Reducer::new(count, ReductionMode::Modulo);
+            assert_eq!(reducer.mask, Some(count - 1));
+            let mut x = shift as u64;
+            for _ in 0..10_000 {
+                x = crate::mix::splitmix64(x);
+                assert_eq!(reducer.reduce(x), x % count, "{} % {}", x, count);
+            }
+            assert_eq!(reducer.reduce(u64::MAX), u64::MAX % count);
+        }
+        assert_eq!(Reducer::new(1000u32, ReductionMode::Modulo).mask, None);
+        assert_eq!(Reducer::new(0u32, ReductionMode::Modulo).mask, None);
+    }
+
+    // Over the whole of a hash range that is not a multiple of the count,
+    // both modes overload the same number of shards by one key; modulo
+    // puts all of them at the low indices, multiply spreads them out. A
+    // chi-square over blocks of 100 adjacent shards shows the difference.
+    #[test]
+    fn test_multiply_spreads_the_remainder() {
+        let count = 1000u32;
+        // Every hash with 16 significant bits, at the top (multiply reads
+        // high bits) or the bottom (modulo reads low bits) of the word.
+        let tally = |mode: ReductionMode, shift: u32| {
+            let reducer = Reducer::new(count, mode);
+            let mut counts = vec![0u32; count as usize];
+            for h in 0..1u64 << 16 {
+                counts[reducer.reduce(h << shift) as usize] += 1;
+            }
+            counts
+        };
+        let block_chi_square = |counts: &[u32]| {
+            let expected = (1u64 << 16) as f64 / 10.0;
+            counts
+                .chunks(100)
+                .map(|block| {
+                    let diff = block.iter().sum::<u32>() as f64 - expected;
+                    diff * diff / expected
+                })
+                .sum::<f64>()
+        };
+        let modulo = tally(ReductionMode::Modulo, 0);
+        let multiply = tally(ReductionMode::Multiply, 48);
+
+        for counts in [&modulo, &multiply] {
+            assert!(counts.iter().all(|&c| c == 65 || c == 66));
+            assert_eq!(counts.iter().filter(|&&c| c == 66).count(), (1 << 16) % 1000);
+        }
+        assert!(modulo[..536].iter().all(|&c| c == 66));
+        let (modulo_chi, multiply_chi) = (block_chi_square(&modulo), block_chi_square(&multiply));
+        assert!(modulo_chi > 3.0, "{}", modulo_chi);
+        assert!(multiply_chi < 0.1, "{}", multiply_chi);
+    }
+
+    #[test]
+    fn test_multiply_stays_in_range() {
+        for count in [1, 2, 1000, 1024, u32::MAX] {
+            let reducer = Reducer::new(count, ReductionMode::Multiply);
+            assert_eq!(reducer.reduce(0), 0);
+            assert_eq!(reducer.reduce(u64::MAX), count - 1);
+            assert_eq!(reducer.reduce(1 << 63), count / 2);
+        }
+    }
+
+    #[test]
+    fn test_index_widths_agree() {
+        for mode in [ReductionMode::Modulo, ReductionMode::Multiply] {
+            for count in [1u16, 3, 1000, 1024, u16::MAX] {
+                let narrow = Reducer::new(count, mode);
+                let wide = Reducer::new(count as u64, mode);
+                let mut x = count as u64;
+                for _ in 0..10_000 {
+                    x = crate::mix::splitmix64(x);
+                    assert_eq!(narrow.reduce(x) as u64, wide.reduce(x));
+                }
+            }
+        }
+
+        // Counts past `u32::MAX` reach indices a `u32` cannot hold.
+        let count = 3 * (1u64 << 32) + 7;
+        let reducer = Reducer::new(count, ReductionMode::Modulo);
+        let mut x = 0u64;
+        let mut above = false;
+        for _ in 0..1000 {
+            x = crate::mix::splitmix64(x);
+            assert_eq!(reducer.reduce(x), x % count);
+            above |= reducer.reduce(x) > u32::MAX as u64;
+        }
+        assert!(above);
+        assert_eq!(Reducer::new(count, ReductionMode::Multiply).reduce(u64::MAX), count - 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_count_still_panics() {
+        Reducer::new(0u32, ReductionMode::Modulo).reduce(42);
+    }
+}
@@ -0,0 +1,257 @@
+// File: src/reference.rs
+//
+// Portable scalar models of the SIMD kernels in `simd.rs`. They compute the
+// same raw hash bit-for-bit without any intrinsics, so tests can check the
+// SIMD output on hosts that have the feature and still exercise the kernel
+// arithmetic on hosts that don't. Adaptive mode (`adaptive.rs`) also runs
+// them in place of a kernel when they time faster.
+#![allow(dead_code)]
+
+fn chunk_padded<const N: usize>(chunk: &[u8]) -> [u8; N] {
+    debug_assert!(chunk.len() <= N);
+    let mut padded = [0u8; N];
+    padded[..chunk.len()].copy_from_slice(chunk);
+    padded
+}
+
+fn lane_u32(bytes: &[u8], lane: usize) -> u32 {
+    let start = lane * 4;
+    u32::from_le_bytes([bytes[start], bytes[start + 1], bytes[start + 2], bytes[start + 3]])
+}
+
+/// Odd weights the AVX-512 kernel multiplies each 32-bit lane by before the
+/// lane sum, so permuting lanes changes the sum. They are unrelated
+/// pseudo-random values (SplitMix64 outputs) rather than multiples of one
+/// constant, which would let permutations of structured keys collide.
+pub(crate) const AVX512_LANE_WEIGHTS: [u32; 16] = {
+    let mut weights = [0u32; 16];
+    let mut state = 0u64;
+    let mut lane = 0;
+    while lane < 16 {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        weights[lane] = (z ^ (z >> 31)) as u32 | 1;
+        lane += 1;
+    }
+    weights
+};
+
+// MurmurHash3's 32-bit finalizer: xorshifts and odd multiplies.
+#[inline(always)]
+fn fmix32(mut x: u32) -> u32 {
+    x = (x ^ (x >> 16)).wrapping_mul(0x85EB_CA6B);
+    x = (x ^ (x >> 13)).wrapping_mul(0xC2B2_AE35);
+    x ^ (x >> 16)
+}
+
+/// Folds one chunk's weighted lane sum into the running hash of the
+/// lane-sum kernels (AVX-512 and SIMD128 over 64-byte chunks, AVX2 over
+/// 32-byte ones). The chunk index and the multiply make the fold
+/// order-dependent, so swapping chunks moves the hash.
+#[inline(always)]
+pub(crate) fn chunk_fold(hash: u32, chunk_index: usize, lane_sum: u32) -> u32 {
+    let position = (chunk_index as u32).wrapping_add(1).wrapping_mul(0x27D4_EB2F);
+    (hash ^ fmix32(lane_sum ^ position)).wrapping_mul(0x1656_67B1)
+}
+
+/// Lane-sum running hash before the first chunk; zero for seed 0.
+#[inline(always)]
+pub(crate) fn chunk_initial(seed: u64) -> u32 {
+    crate::mix::fmix64(seed) as u32
+}
+
+/// Final lane-sum avalanche; mixing in the length keeps zero padding from
+/// colliding with explicit zero bytes. A `folded_len` of 0 leaves it out,
+/// for fixed-length keys where only the bytes should count.
+#[inline(always)]
+pub(crate) fn chunk_finish(hash: u32, folded_len: usize) -> u32 {
+    fmix32(hash ^ folded_len as u32)
+}
+
+pub(crate) fn avx512_hash(key: &[u8]) -> u32 {
+    avx512_hash_seeded(key, 0)
+}
+
+pub(crate) fn avx512_hash_seeded(key: &[u8], seed: u64) -> u32 {
+    avx512_hash_with_len(key, seed, key.len())
+}
+
+/// `avx512_hash_seeded` folding `folded_len` in place of the key's length.
+pub(crate) fn avx512_hash_with_len(key: &[u8], seed: u64, folded_len: usize) -> u32 {
+    let mut hash = chunk_initial(seed);
+    for (index, chunk) in key.chunks(64).enumerate() {
+        let padded = chunk_padded::<64>(chunk);
+        let lane_sum = AVX512_LANE_WEIGHTS.iter().enumerate().fold(0u32, |acc, (lane, weight)| {
+            acc.wrapping_add(lane_u32(&padded, lane).wrapping_mul(*weight))
+        });
+        hash = chunk_fold(hash, index, lane_sum);
+    }
+    chunk_finish(hash, folded_len)
+}
+
+/// Distinct odd weights the AVX2 kernel multiplies each 32-bit lane by
+/// before summing, so every lane, and its position, affects the hash.
+pub(crate) const AVX2_LANE_WEIGHTS: [u32; 8] = [
+    0x9E37_79B1, 0x85EB_CA77, 0xC2B2_AE3D, 0x27D4_EB2F,
+    0x1656_67B1, 0xFD70_46C5, 0xB55A_4F09, 0x7FEB_352D,
+];
+
+pub(crate) fn avx2_hash(key: &[u8]) -> u32 {
+    avx2_hash_seeded(key, 0)
+}
+
+/// The AVX-512 construction over 32-byte chunks: each chunk's weighted
+/// lane sum is folded in with its index, and the length is mixed in last.
+pub(crate) fn avx2_hash_seeded(key: &[u8], seed: u64) -> u32 {
+    avx2_hash_with_len(key, seed, key.len())
+}
+
+/// `avx2_hash_seeded` folding `folded_len` in place of the key's length.
+pub(crate) fn avx2_hash_with_len(key: &[u8], seed: u64, folded_len: usize) -> u32 {
+    let mut hash = chunk_initial(seed);
+    for (index, chunk) in key.chunks(32).enumerate() {
+        let padded = chunk_padded::<32>(chunk);
+        let lane_sum = AVX2_LANE_WEIGHTS.iter().enumerate().fold(0u32, |acc, (lane, weight)| {
+            acc.wrapping_add(lane_u32(&padded, lane).wrapping_mul(*weight))
+        });
+        hash = chunk_fold(hash, index, lane_sum);
+    }
+    chunk_finish(hash, folded_len)
+}
+
+/// The AES-NI hash's starting state and round keys, drawn from a SplitMix64
+/// stream seeded with `seed`: `[initial, k1, k2]`. Even seed 0 gives
+/// nonzero, distinct keys, so no round is keyed with zero.
+#[inline(always)]
+pub(crate) fn aesni_keys(seed: u64) -> [[u8; 16]; 3] {
+    let word = |i: u64| {
+        crate::mix::splitmix64(seed.wrapping_add(i.wrapping_mul(0x9E37_79B9_7F4A_7C15)))
+    };
+    let mut keys = [[0u8; 16]; 3];
+    for (i, key) in keys.iter_mut().enumerate() {
+        key[..8].copy_from_slice(&word(2 * i as u64).to_le_bytes());
+        key[8..].copy_from_slice(&word(2 * i as u64 + 1).to_le_bytes());
+    }
+    keys
+}
+
+pub(crate) fn aesni_hash(key: &[u8]) -> u32 {
+    aesni_hash_seeded(key, 0)
+}
+
+/// Each 16-byte chunk is XORed into the state, then two `aesenc` rounds
+/// (keys `k1`, `k2`) diffuse every byte across the whole state before the
+/// next chunk. The length is XORed into the low half (so zero padding does
+/// not collide with explicit zero bytes), and an `aesenc`, `aesenclast`
+/// pair finalizes before the low 32 bits are taken.
+pub(crate) fn aesni_hash_seeded(key: &[u8], seed: u64) -> u32 {
+    aesni_hash_with_len(key, seed, key.len())
+}
+
+/// `aesni_hash_seeded` XORing `folded_len` in place of the key's length; 0
+/// leaves the state as the chunks left it.
+pub(crate) fn aesni_hash_with_len(key: &[u8], seed: u64, folded_len: usize) -> u32 {
+    let [mut hash, k1, k2] = aesni_keys(seed);
+    for chunk in key.chunks(16) {
+        let data = chunk_padded::<16>(chunk);
+        for (byte, data) in hash.iter_mut().zip(data.iter()) {
+            *byte ^= data;
+        }
+        hash = aesenc(aesenc(hash, k1), k2);
+    }
+    for (byte, len) in hash.iter_mut().zip((folded_len as u64).to_le_bytes()) {
+        *byte ^= len;
+    }
+    hash = aesenclast(aesenc(hash, k1), k2);
+    lane_u32(&hash, 0)
+}
+
+/// CRC32C register before the first byte: the standard all-ones value for
+/// seed 0, which makes `crc32c_hash` the ordinary CRC-32C (Castagnoli).
+#[inline(always)]
+pub(crate) fn crc32c_initial(seed: u64) -> u32 {
+    !(crate::mix::fmix64(seed) as u32)
+}
+
+pub(crate) fn crc32c_hash(key: &[u8]) -> u32 {
+    crc32c_hash_seeded(key, 0)
+}
+
+/// Bitwise CRC-32C over the key bytes in order, as the SSE4.2 `crc32`
+/// instructions compute it.
+pub(crate) fn crc32c_hash_seeded(key: &[u8], seed: u64) -> u32 {
+    const POLY: u32 = 0x82F6_3B78; // Castagnoli, bit-reversed
+    let mut crc = crc32c_initial(seed);
+    for &byte in key {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (POLY & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+/// One AES encryption round (`ShiftRows`, `SubBytes`, `MixColumns`,
+/// `AddRoundKey`), matching `_mm_aesenc_si128` on a column-major state.
+pub(crate) fn aesenc(state: [u8; 16], round_key: [u8; 16]) -> [u8; 16] {
+    let shifted = shift_sub(state);
+    let mut out = [0u8; 16];
+    for col in 0..4 {
+        let c = &shifted[col * 4..col * 4 + 4];
+        out[col * 4] = xtime(c[0]) ^ (xtime(c[1]) ^ c[1]) ^ c[2] ^ c[3];
+        out[col * 4 + 1] = c[0] ^ xtime(c[1]) ^ (xtime(c[2]) ^ c[2]) ^ c[3];
+        out[col * 4 + 2] = c[0] ^ c[1] ^ xtime(c[2]) ^ (xtime(c[3]) ^ c[3]);
+        out[col * 4 + 3] = (xtime(c[0]) ^ c[0]) ^ c[1] ^ c[2] ^ xtime(c[3]);
+    }
+    add_round_key(out, round_key)
+}
+
+/// The last AES round, without `MixColumns`, matching
+/// `_mm_aesenclast_si128`.
+pub(crate) fn aesenclast(state: [u8; 16], round_key: [u8; 16]) -> [u8; 16] {
+    add_round_key(shift_sub(state), round_key)
+}
+
+// `ShiftRows` and `SubBytes`, which commute.
+fn shift_sub(state: [u8; 16]) -> [u8; 16] {
+    let mut shifted = [0u8; 16];
+    for col in 0..4 {
+        for row in 0..4 {
+            shifted[col * 4 + row] = SBOX[state[((col + row) % 4) * 4 + row] as usize];
+        }
+    }
+    shifted
+}
+
+fn add_round_key(mut state: [u8; 16], round_key: [u8; 16]) -> [u8; 16] {
+    for (byte, key) in state.iter_mut().zip(round_key.iter()) {
+        *byte ^= key;
+    }
+    state
+}
+
+fn xtime(b: u8) -> u8 {
+    (b << 1) ^ if b & 0x80 != 0 { 0x1b } else { 0 }
+}
+
+const SBOX: [u8; 256] = [
+    0x63, 0x7c, 0x77, 0x7b, 0xf2, 0x6b, 0x6f, 0xc5, 0x30, 0x01, 0x67, 0x2b, 0xfe, 0xd7, 0xab, 0x76,
+    0xca, 0x82, 0xc9, 0x7d, 0xfa, 0x59, 0x47, 0xf0, 0xad, 0xd4, 0xa2, 0xaf, 0x9c, 0xa4, 0x72, 0xc0,
+    0xb7, 0xfd, 0x93, 0x26, 0x36, 0x3f, 0xf7, 0xcc, 0x34, 0xa5, 0xe5, 0xf1, 0x71, 0xd8, 0x31, 0x15,
+    0x04, 0xc7, 0x23, 0xc3, 0x18, 0x96, 0x05, 0x9a, 0x07, 0x12, 0x80, 0xe2, 0xeb, 0x27, 0xb2, 0x75,
+    0x09, 0x83, 0x2c, 0x1a, 0x1b, 0x6e, 0x5a, 0xa0, 0x52, 0x3b, 0xd6, 0xb3, 0x29, 0xe3, 0x2f, 0x84,
+    0x53, 0xd1, 0x00, 0xed, 0x20, 0xfc, 0xb1, 0x5b, 0x6a, 0xcb, 0xbe, 0x39, 0x4a, 0x4c, 0x58, 0xcf,
+    0xd0, 0xef, 0xaa, 0xfb, 0x43, 0x4d, 0x33, 0x85, 0x45, 0xf9, 0x02, 0x7f, 0x50, 0x3c, 0x9f, 0xa8,
+    0x51, 0xa3, 0x40, 0x8f, 0x92, 0x9d, 0x38, 0xf5, 0xbc, 0xb6, 0xda, 0x21, 0x10, 0xff, 0xf3, 0xd2,
+    0xcd, 0x0c, 0x13, 0xec, 0x5f, 0x97, 0x44, 0x17, 0xc4, 0xa7, 0x7e, 0x3d, 0x64, 0x5d, 0x19, 0x73,
+    0x60, 0x81, 0x4f, 0xdc, 0x22, 0x2a, 0x90, 0x88, 0x46, 0xee, 0xb8, 0x14, 0xde, 0x5e, 0x0b, 0xdb,
+    0xe0, 0x32, 0x3a, 0x0a, 0x49, 0x06, 0x24, 0x5c, 0xc2, 0xd3, 0xac, 0x62, 0x91, 0x95, 0xe4, 0x79,
+    0xe7, 0xc8, 0x37, 0x6d, 0x8d, 0xd5, 0x4e, 0xa9, 0x6c, 0x56, 0xf4, 0xea, 0x65, 0x7a, 0xae, 0x08,
+    0xba, 0x78, 0x25, 0x2e, 0x1c, 0xa6, 0xb4, 0xc6, 0xe8, 0xdd, 0x74, 0x1f, 0x4b, 0xbd, 0x8b, 0x8a,
+    0x70, 0x3e, 0xb5, 0x66, 0x48, 0x03, 0xf6, 0x0e, 0x61, 0x35, 0x57, 0xb9, 0x86, 0xc1, 0x1d, 0x9e,
+    0xe1, 0xf8, 0x98, 0x11, 0x69, 0xd9, 0x8e, 0x94, 0x9b, 0x1e, 0x87, 0xe9, 0xce, 0x55, 0x28, 0xdf,
+    0x8c, 0xa1, 0x89, 0x0d, 0xbf, 0xe6, 0x42, 0x68, 0x41, 0x99, 0x2d, 0x0f, 0xb0, 0x54, 0xbb, 0x16,
+];
+
@@ -0,0 +1,255 @@
+// File: src/rendezvous.rs
+//
+// Rendezvous (highest-random-weight) placement: every candidate shard gets a
+// pseudo-random score derived from the key hash and the shard id, and the
+// key goes to the highest score. Removing a candidate only moves the keys
+// that were on it.
+//
+// Weighted shards use the logarithmic method (Schindelhauer and Schomaker):
+// shard `i` scores `w_i / -ln(u_i)` for a uniform `u_i` in (0, 1), which
+// picks it with probability `w_i / sum(w)`. Raising one weight only moves
+// keys onto that shard.
+
+#[cfg(feature = "std")]
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use crate::mix::splitmix64;
+use crate::FastShard;
+
+// Score of `shard` for a key whose full hash is `hash`.
+pub(crate) fn score(hash: u64, shard: u32) -> u64 {
+    splitmix64(hash ^ splitmix64(shard as u64))
+}
+
+// Highest-scoring shard among `candidates`; ties go to the smaller id.
+pub(crate) fn select(hash: u64, candidates: impl IntoIterator<Item = u32>) -> Option<u32> {
+    candidates
+        .into_iter()
+        .map(|shard| (score(hash, shard), core::cmp::Reverse(shard)))
+        .max()
+        .map(|(_, core::cmp::Reverse(shard))| shard)
+}
+
+// Weighted-rendezvous winner for `hash`; zero-weight shards never win, and
+// ties go to the smaller id.
+#[cfg(feature = "std")]
+pub(crate) fn select_weighted(hash: u64, weights: &[u32]) -> u32 {
+    let mut best = (0.0f64, 0u32);
+    for (shard, &weight) in weights.iter().enumerate() {
+        if weight == 0 {
+            continue;
+        }
+        // The top 53 bits, centred in their interval, so `u` is never 0 or 1.
+        let u = ((score(hash, shard as u32) >> 11) as f64 + 0.5) / (1u64 << 53) as f64;
+        let weighted = weight as f64 / -u.ln();
+        if weighted > best.0 {
+            best = (weighted, shard as u32);
+        }
+    }
+    best.1
+}
+
+impl FastShard {
+    /// A default-config instance with one shard per entry of `weights`,
+    /// each receiving a share of the keys proportional to its weight, e.g.
+    /// `[1, 1, 2]` sends half the keys to shard 2. Every sharding path uses
+    /// weighted rendezvous hashing in place of the usual mapping, so
+    /// changing one shard's weight only moves keys onto it (when raised) or
+    /// off it (when lowered). A zero weight takes a shard out of rotation.
+    /// O(shard_count) per key. Requires the `std` feature.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `weights` is empty or all zero.
+    #[cfg(feature = "std")]
+    pub fn with_weights(weights: Vec<u32>) -> Self {
+        assert!(weights.iter().any(|&w| w > 0), "weights must include a nonzero entry");
+        let mut shard = Self::new(weights.len() as u32);
+        shard.weights = Some(Arc::from(weights));
+        shard
+    }
+
+    /// Maps `key` onto one of the `live` shards by rendezvous hashing, so
+    /// when a shard leaves `live` only its own keys move. The result is
+    /// independent of the order of `live`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `live` is empty.
+    pub fn shard_among(&self, key: &[u8], live: &[u32]) -> u32 {
+        select(self.hash64(key), live.iter().copied()).expect("live shard set must not be empty")
+    }
+
+    /// Shards `key` by rendezvous hashing over all `shard_count` shards:
+    /// each shard scores the key's hash (from the usual algorithm
+    /// selection) and the highest score wins. Growing the shard count from
+    /// `n` to `n + 1` moves only the keys the new shard outscores, about
+    /// `1 / (n + 1)` of them, and shrinking it moves only the removed
+    /// shard's keys. O(shard_count) per key; [`jump_shard`] gives the same
+    /// growth guarantee in O(log shard_count). Equal to `shard_among` with
+    /// every shard live.
+    ///
+    /// [`jump_shard`]: FastShard::jump_shard
+    pub fn rendezvous_shard(&self, key: &[u8]) -> u32 {
+        select(self.hash64(key), 0..self.shard_count).expect("shard count is nonzero")
+    }
+
+    /// The `n` highest-scoring shards for `key` across all shards, best
+    /// first, using the same rendezvous scores as [`shard_among`]; the first
+    /// entry is what `shard_among` picks with every shard live. `n` is capped
+    /// at the shard count. For replicated storage, place a key on the first
+    /// entry (its [`rendezvous_shard`]) with replicas on the rest; they are
+    /// distinct, and losing a shard promotes each of its keys' next replica.
+    ///
+    /// [`rendezvous_shard`]: FastShard::rendezvous_shard
+    ///
+    /// [`shard_among`]: FastShard::shard_among
+    pub fn ranked_shards(&self, key: &[u8], n: usize) -> Vec<u32> {
+        let hash = self.hash64(key);
+        let n = n.min(self.shard_count as usize);
+        let mut ranked: Vec<(core::cmp::Reverse<u64>, u32)> =
+            (0..self.shard_count).map(|shard| (core::cmp::Reverse(score(hash, shard)), shard)).collect();
+        if n < ranked.len() {
+            ranked.select_nth_unstable(n);
+            ranked.truncate(n);
+        }
+        ranked.sort_unstable();
+        ranked.into_iter().map(|(_, shard)| shard).collect()
+    }
+
+    /// `n` distinct shards to hold `key` and its replicas, primary first:
+    /// [`ranked_shards`](Self::ranked_shards) under the name replicated
+    /// stores look for. `n` is capped at the shard count.
+    pub fn shard_replicas(&self, key: &[u8], n: usize) -> Vec<u32> {
+        self.ranked_shards(key, n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shard_among_only_moves_removed_shard_keys() {
+        let shard = FastShard::new(16);
+        let live: Vec<u32> = (0..16).collect();
+        let degraded: Vec<u32> = live.iter().copied().filter(|&s| s != 5).collect();
+
+        let mut moved = 0;
+        for i in 0..5000u32 {
+            let key = format!("key-{}", i);
+            let before = shard.shard_among(key.as_bytes(), &live);
+            let after = shard.shard_among(key.as_bytes(), &degraded);
+            assert_ne!(after, 5);
+            if before != 5 {
+                assert_eq!(before, after);
+            } else {
+                moved += 1;
+            }
+        }
+        // Roughly 1/16 of the keys lived on shard 5.
+        assert!(moved > 200 && moved < 450, "moved {}", moved);
+
+        let mut reversed = live.clone();
+        reversed.reverse();
+        assert_eq!(shard.shard_among(b"abc", &live), shard.shard_among(b"abc", &reversed));
+    }
+
+    #[test]
+    fn test_rendezvous_shard_growth_moves_one_in_n_plus_one() {
+        let eight = FastShard::new(8);
+        let nine = FastShard::new(9);
+        let all: Vec<u32> = (0..9).collect();
+        let keys = 20_000u32;
+        let mut moved = 0;
+        for i in 0..keys {
+            let key = format!("key-{}", i);
+            let before = eight.rendezvous_shard(key.as_bytes());
+            let after = nine.rendezvous_shard(key.as_bytes());
+            assert_eq!(after, nine.shard_among(key.as_bytes(), &all));
+            if before != after {
+                // Only the new shard takes keys.
+                assert_eq!(after, 8);
+                moved += 1;
+            }
+        }
+        let fraction = moved as f64 / keys as f64;
+        assert!((fraction - 1.0 / 9.0).abs() < 0.01, "moved {}", fraction);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_weighted_shards_get_proportional_load() {
+        use rand::{Rng, SeedableRng};
+
+        let shard = FastShard::with_weights(vec![1, 1, 2]);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(274);
+        let mut counts = [0u32; 3];
+        for _ in 0..100_000 {
+            let key: [u8; 16] = rng.gen();
+            counts[shard.shard(&key) as usize] += 1;
+        }
+        let share = |count: u32| count as f64 / 100_000.0;
+        assert!((share(counts[2]) - 0.5).abs() < 0.01, "{:?}", counts);
+        assert!((share(counts[0]) - 0.25).abs() < 0.01, "{:?}", counts);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_raising_a_weight_only_moves_keys_onto_that_shard() {
+        let before = FastShard::with_weights(vec![1, 1, 2, 0]);
+        let after = FastShard::with_weights(vec![1, 3, 2, 0]);
+        let mut moved = 0;
+        for i in 0..20_000u32 {
+            let key = format!("key-{}", i);
+            let (old, new) = (before.shard(key.as_bytes()), after.shard(key.as_bytes()));
+            assert_ne!(new, 3);
+            if old != new {
+                assert_eq!(new, 1);
+                moved += 1;
+            }
+        }
+        // Shard 1 goes from 1/4 to 1/2 of the keys.
+        assert!((moved as f64 / 20_000.0 - 0.25).abs() < 0.02, "moved {}", moved);
+    }
+
+    #[test]
+    fn test_ranked_shards_leads_with_primary() {
+        let shard = FastShard::new(32);
+        let all: Vec<u32> = (0..32).collect();
+        for i in 0..500u32 {
+            let key = format!("key-{}", i);
+            let ranked = shard.ranked_shards(key.as_bytes(), 4);
+            assert_eq!(ranked.len(), 4);
+            assert_eq!(ranked[0], shard.shard_among(key.as_bytes(), &all));
+            assert_eq!(ranked[0], shard.rendezvous_shard(key.as_bytes()));
+            assert_eq!(ranked, shard.ranked_shards(key.as_bytes(), 4));
+            assert_eq!(&shard.ranked_shards(key.as_bytes(), 8)[..4], &ranked[..]);
+
+            let mut distinct = ranked.clone();
+            distinct.sort_unstable();
+            distinct.dedup();
+            assert_eq!(distinct.len(), 4);
+        }
+        assert_eq!(shard.ranked_shards(b"k", 100).len(), 32);
+        assert!(shard.ranked_shards(b"k", 0).is_empty());
+    }
+
+    #[test]
+    fn test_shard_replicas_are_distinct_and_lead_with_primary() {
+        let shard = FastShard::new(8);
+        for i in 0..1000u32 {
+            let key = format!("key-{}", i);
+            let replicas = shard.shard_replicas(key.as_bytes(), 3);
+            assert_eq!(replicas.len(), 3);
+            assert_eq!(replicas[0], shard.rendezvous_shard(key.as_bytes()));
+            assert!(replicas[0] != replicas[1] && replicas[1] != replicas[2] && replicas[0] != replicas[2]);
+
+            // Losing the primary promotes the first replica.
+            let survivors: Vec<u32> = (0..8).filter(|&s| s != replicas[0]).collect();
+            assert_eq!(shard.shard_among(key.as_bytes(), &survivors), replicas[1]);
+        }
+        assert_eq!(shard.shard_replicas(b"k", 20).len(), 8);
+    }
+}
@@ -0,0 +1,94 @@
+// File: src/rolling.rs
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crate::FastShard;
+
+/// When a [`RollingShard`] moves on to the next shard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RollTrigger {
+    /// After this many keys have been placed on the active shard.
+    Keys(u64),
+    /// After this many key bytes have been placed on the active shard.
+    Bytes(u64),
+}
+
+/// Sequential placement for append-mostly workloads: new keys fill the
+/// active shard until the roll trigger fires, then move to the next shard,
+/// wrapping after the last one. Keys written before rolling began are found
+/// with [`RollingShard::locate`], which uses the wrapped `FastShard`'s hash.
+///
+/// The roll point depends only on the order and sizes of placed keys, so
+/// replaying the same sequence yields the same placements.
+#[derive(Debug)]
+pub struct RollingShard {
+    shard: FastShard,
+    trigger: RollTrigger,
+    placed: AtomicU64,
+}
+
+impl FastShard {
+    /// Switches to sequential placement; see [`RollingShard`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the trigger's threshold is zero.
+    pub fn rolling(self, trigger: RollTrigger) -> RollingShard {
+        let (RollTrigger::Keys(threshold) | RollTrigger::Bytes(threshold)) = trigger;
+        assert!(threshold > 0, "roll threshold must be nonzero");
+        RollingShard {
+            shard: self,
+            trigger,
+            placed: AtomicU64::new(0),
+        }
+    }
+}
+
+impl RollingShard {
+    /// Places a new key on the active shard, rolling over when the trigger
+    /// fires. A key goes where its first byte would land.
+    pub fn place(&self, key: &[u8]) -> u32 {
+        let (weight, threshold) = match self.trigger {
+            RollTrigger::Keys(threshold) => (1, threshold),
+            RollTrigger::Bytes(threshold) => (key.len() as u64, threshold),
+        };
+        let start = self.placed.fetch_add(weight, Ordering::Relaxed);
+        ((start / threshold) % self.shard.shard_count as u64) as u32
+    }
+
+    /// The shard the next key will be placed on.
+    pub fn active_shard(&self) -> u32 {
+        let (RollTrigger::Keys(threshold) | RollTrigger::Bytes(threshold)) = self.trigger;
+        ((self.placed.load(Ordering::Relaxed) / threshold) % self.shard.shard_count as u64) as u32
+    }
+
+    /// Finds a key written before rolling began, by hash.
+    pub fn locate(&self, key: &[u8]) -> u32 {
+        self.shard.shard(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rolling_fills_shards_in_order() {
+        let rolling = FastShard::new(3).rolling(RollTrigger::Keys(2));
+        let placed: Vec<u32> = (0..8).map(|i| rolling.place(format!("k{}", i).as_bytes())).collect();
+        assert_eq!(placed, vec![0, 0, 1, 1, 2, 2, 0, 0]);
+        assert_eq!(rolling.active_shard(), 1);
+
+        let by_bytes = FastShard::new(4).rolling(RollTrigger::Bytes(10));
+        let sizes = [4usize, 4, 4, 8, 1, 9];
+        let placed: Vec<u32> = sizes.iter().map(|&len| by_bytes.place(&vec![0u8; len])).collect();
+        // Keys start at byte offsets 0, 4, 8, 12, 20 and 21.
+        assert_eq!(placed, vec![0, 0, 0, 1, 2, 2]);
+
+        // Replaying the same sequence rolls at the same point.
+        let replay = FastShard::new(4).rolling(RollTrigger::Bytes(10));
+        let again: Vec<u32> = sizes.iter().map(|&len| replay.place(&vec![0u8; len])).collect();
+        assert_eq!(again, placed);
+
+        assert_eq!(rolling.locate(b"legacy"), FastShard::new(3).shard(b"legacy"));
+    }
+}
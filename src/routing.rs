@@ -0,0 +1,63 @@
+// File: src/routing.rs
+//
+// Canary routing: a stable, hash-derived split of keys between a canary and
+// a stable bucket. It is salted apart from placement, so which keys are in
+// the canary says nothing about which shard they live on.
+
+use crate::mix::splitmix64;
+use crate::FastShard;
+
+// Keeps canary membership independent of shard placement.
+const CANARY_SALT: u64 = 0x6361_6e61_7279_0001;
+
+/// Which bucket [`FastShard::canary_route`] puts a key in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Route {
+    /// The key gets the new behavior under test.
+    Canary,
+    /// The key keeps the current behavior.
+    Stable,
+}
+
+impl FastShard {
+    /// Puts roughly `canary_fraction` of keys in the canary bucket, keyed on
+    /// the key's hash so a key always gets the same treatment. Raising the
+    /// fraction only adds keys to the canary; none move back to stable.
+    /// Fractions outside `0.0..=1.0` are clamped.
+    pub fn canary_route(&self, key: &[u8], canary_fraction: f64) -> Route {
+        // Top 53 bits as a uniform value in [0, 1).
+        let point = (splitmix64(self.hash64(key) ^ CANARY_SALT) >> 11) as f64 / (1u64 << 53) as f64;
+        if point < canary_fraction.clamp(0.0, 1.0) {
+            Route::Canary
+        } else {
+            Route::Stable
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canary_route_fraction_and_stability() {
+        let shard = FastShard::new(64);
+        let keys: Vec<String> = (0..20_000).map(|i| format!("user-{}", i)).collect();
+
+        let canary = keys
+            .iter()
+            .filter(|key| shard.canary_route(key.as_bytes(), 0.1) == Route::Canary)
+            .count();
+        assert!((1800..=2200).contains(&canary), "{} canary keys", canary);
+
+        for key in keys.iter().take(1000) {
+            let route = shard.canary_route(key.as_bytes(), 0.1);
+            assert_eq!(route, shard.canary_route(key.as_bytes(), 0.1));
+            if route == Route::Canary {
+                assert_eq!(shard.canary_route(key.as_bytes(), 0.2), Route::Canary);
+            }
+            assert_eq!(shard.canary_route(key.as_bytes(), 0.0), Route::Stable);
+            assert_eq!(shard.canary_route(key.as_bytes(), 1.0), Route::Canary);
+        }
+    }
+}
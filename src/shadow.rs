@@ -0,0 +1,107 @@
+// File: src/shadow.rs
+use alloc::boxed::Box;
+use core::fmt;
+
+use crate::FastShard;
+
+type DivergenceCallback<'a> = Box<dyn Fn(u64, u32, u32) + Send + Sync + 'a>;
+
+/// Runs a candidate `FastShard` alongside the live one during a config
+/// migration. [`shard`](ShadowShard::shard) always answers with the live
+/// placement; whenever the candidate would place a key elsewhere, the
+/// divergence callback receives `(key_hash, live, candidate)`, where
+/// `key_hash` is the live instance's full hash of the key.
+pub struct ShadowShard<'a> {
+    live: &'a FastShard,
+    candidate: &'a FastShard,
+    on_divergence: Option<DivergenceCallback<'a>>,
+}
+
+impl FastShard {
+    /// Shadows `candidate` behind this instance; see [`ShadowShard`].
+    pub fn shadow<'a>(&'a self, candidate: &'a FastShard) -> ShadowShard<'a> {
+        ShadowShard {
+            live: self,
+            candidate,
+            on_divergence: None,
+        }
+    }
+}
+
+impl<'a> ShadowShard<'a> {
+    /// Sets the callback that records divergences.
+    pub fn on_divergence<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(u64, u32, u32) + Send + Sync + 'a,
+    {
+        self.on_divergence = Some(Box::new(callback));
+        self
+    }
+
+    /// The live shard for `key`, reporting a divergence if the candidate
+    /// disagrees.
+    pub fn shard(&self, key: &[u8]) -> u32 {
+        let live = self.live.shard(key);
+        if let Some(callback) = &self.on_divergence {
+            let candidate = self.candidate.shard(key);
+            if candidate != live {
+                callback(self.live.hash64(key), live, candidate);
+            }
+        }
+        live
+    }
+
+    pub fn live(&self) -> &FastShard {
+        self.live
+    }
+
+    pub fn candidate(&self) -> &FastShard {
+        self.candidate
+    }
+}
+
+impl fmt::Debug for ShadowShard<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShadowShard")
+            .field("live", &self.live)
+            .field("candidate", &self.candidate)
+            .field("on_divergence", &self.on_divergence.as_ref().map(|_| "<fn>"))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_shadow_returns_live_and_reports_divergence() {
+        let live = FastShard::new(64);
+        let candidate = FastShard::new(65);
+        let divergences = Mutex::new(Vec::new());
+        let shadow = live
+            .shadow(&candidate)
+            .on_divergence(|hash, live, candidate| divergences.lock().unwrap().push((hash, live, candidate)));
+
+        let mut expected = Vec::new();
+        for i in 0..500u32 {
+            let key = format!("key-{}", i);
+            let placed = shadow.shard(key.as_bytes());
+            assert_eq!(placed, live.shard(key.as_bytes()));
+            let moved_to = candidate.shard(key.as_bytes());
+            if moved_to != placed {
+                expected.push((live.hash64(key.as_bytes()), placed, moved_to));
+            }
+        }
+        assert!(!expected.is_empty());
+        drop(shadow);
+        assert_eq!(divergences.into_inner().unwrap(), expected);
+
+        let same = FastShard::new(64);
+        let quiet = live.shadow(&same).on_divergence(|_, _, _| panic!("configs agree"));
+        for i in 0..500u32 {
+            quiet.shard(&i.to_le_bytes());
+        }
+    }
+}
@@ -0,0 +1,166 @@
+// File: src/simd.rs
+//
+// Raw x86_64 SIMD kernels. Each function is compiled for its own target
+// feature, so callers must confirm the feature is present (compile-time or
+// via `is_x86_feature_detected!`) before calling it.
+//
+// The kernels are compiled whenever their cargo feature is on, regardless of
+// the build's `-C target-feature` flags, and `FastShard` calls one only when
+// the `CpuFeatures` it detected at construction (see `cpu.rs`) reports the
+// feature. The choice between a kernel and its XXH3 fallback is therefore
+// fixed for the life of an instance.
+//
+// Lanes are read as little-endian integers. x86_64 is always little-endian,
+// so this matches the explicit `from_le_bytes` reads in the scalar models and
+// keeps placement identical on big-endian hosts that use those models.
+#![allow(dead_code)]
+
+use core::arch::x86_64::*;
+
+#[cfg(feature = "avx512")]
+#[target_feature(enable = "avx512f")]
+pub(crate) unsafe fn avx512_hash(key: &[u8]) -> u32 {
+    avx512_hash_in(key, 0, key.len(), &mut [0u8; 64])
+}
+
+#[cfg(feature = "avx512")]
+#[target_feature(enable = "avx512f")]
+pub(crate) unsafe fn avx512_hash_seeded(key: &[u8], seed: u64) -> u32 {
+    avx512_hash_in(key, seed, key.len(), &mut [0u8; 64])
+}
+
+/// `avx512_hash_seeded` padding its tail chunk in `scratch`, which must be
+/// all zero and is left all zero, and folding `folded_len` in place of the
+/// key's length.
+#[cfg(feature = "avx512")]
+#[target_feature(enable = "avx512f")]
+pub(crate) unsafe fn avx512_hash_in(key: &[u8], seed: u64, folded_len: usize, scratch: &mut [u8; 64]) -> u32 {
+    use crate::reference::{chunk_finish, chunk_fold, chunk_initial, AVX512_LANE_WEIGHTS};
+
+    let weights = _mm512_loadu_si512(AVX512_LANE_WEIGHTS.as_ptr() as *const _);
+    let mut hash = chunk_initial(seed);
+    for (index, chunk) in key.chunks(64).enumerate() {
+        let vec = if chunk.len() == 64 {
+            _mm512_loadu_si512(chunk.as_ptr() as *const _)
+        } else {
+            debug_assert!(chunk.len() <= 64);
+            scratch[..chunk.len()].copy_from_slice(chunk);
+            let vec = _mm512_loadu_si512(scratch.as_ptr() as *const _);
+            scratch[..chunk.len()].fill(0);
+            vec
+        };
+
+        let lane_sum = _mm512_reduce_add_epi32(_mm512_mullo_epi32(vec, weights));
+        hash = chunk_fold(hash, index, lane_sum as u32);
+    }
+    chunk_finish(hash, folded_len)
+}
+
+#[cfg(feature = "avx2")]
+#[target_feature(enable = "avx2")]
+pub(crate) unsafe fn avx2_hash(key: &[u8]) -> u32 {
+    avx2_hash_in(key, 0, key.len(), &mut [0u8; 64])
+}
+
+#[cfg(feature = "avx2")]
+#[target_feature(enable = "avx2")]
+pub(crate) unsafe fn avx2_hash_seeded(key: &[u8], seed: u64) -> u32 {
+    avx2_hash_in(key, seed, key.len(), &mut [0u8; 64])
+}
+
+/// `avx2_hash_seeded` padding its tail chunk in `scratch`, which must be all
+/// zero and is left all zero, and folding `folded_len` in place of the key's
+/// length.
+#[cfg(feature = "avx2")]
+#[target_feature(enable = "avx2")]
+pub(crate) unsafe fn avx2_hash_in(key: &[u8], seed: u64, folded_len: usize, scratch: &mut [u8; 64]) -> u32 {
+    use crate::reference::{chunk_finish, chunk_fold, chunk_initial, AVX2_LANE_WEIGHTS};
+
+    let w = AVX2_LANE_WEIGHTS.map(|weight| weight as i32);
+    let weights = _mm256_setr_epi32(w[0], w[1], w[2], w[3], w[4], w[5], w[6], w[7]);
+    let mut hash = chunk_initial(seed);
+    for (index, chunk) in key.chunks(32).enumerate() {
+        let vec = if chunk.len() == 32 {
+            _mm256_loadu_si256(chunk.as_ptr() as *const _)
+        } else {
+            debug_assert!(chunk.len() <= 32);
+            scratch[..chunk.len()].copy_from_slice(chunk);
+            let vec = _mm256_loadu_si256(scratch.as_ptr() as *const _);
+            scratch[..chunk.len()].fill(0);
+            vec
+        };
+
+        // Sum the eight weighted lanes into one.
+        let products = _mm256_mullo_epi32(vec, weights);
+        let halves = _mm_add_epi32(_mm256_castsi256_si128(products), _mm256_extracti128_si256::<1>(products));
+        let pairs = _mm_add_epi32(halves, _mm_shuffle_epi32::<0b01_00_11_10>(halves));
+        let lane_sum = _mm_add_epi32(pairs, _mm_shuffle_epi32::<0b10_11_00_01>(pairs));
+        hash = chunk_fold(hash, index, _mm_cvtsi128_si32(lane_sum) as u32);
+    }
+    chunk_finish(hash, folded_len)
+}
+
+#[cfg(feature = "crc32c")]
+#[target_feature(enable = "sse4.2")]
+pub(crate) unsafe fn crc32c_hash(key: &[u8]) -> u32 {
+    crc32c_hash_seeded(key, 0)
+}
+
+/// CRC-32C of the key, folded 8 bytes at a time; the tail goes through a
+/// byte at a time, so no padding (and no scratch) is needed.
+#[cfg(feature = "crc32c")]
+#[target_feature(enable = "sse4.2")]
+pub(crate) unsafe fn crc32c_hash_seeded(key: &[u8], seed: u64) -> u32 {
+    let mut crc = crate::reference::crc32c_initial(seed) as u64;
+    let mut words = key.chunks_exact(8);
+    for word in &mut words {
+        crc = _mm_crc32_u64(crc, u64::from_le_bytes(word.try_into().unwrap()));
+    }
+    let mut crc = crc as u32;
+    for &byte in words.remainder() {
+        crc = _mm_crc32_u8(crc, byte);
+    }
+    !crc
+}
+
+#[cfg(feature = "aesni")]
+#[target_feature(enable = "aes")]
+pub(crate) unsafe fn aesni_hash(key: &[u8]) -> u32 {
+    aesni_hash_in(key, 0, key.len(), &mut [0u8; 64])
+}
+
+#[cfg(feature = "aesni")]
+#[target_feature(enable = "aes")]
+pub(crate) unsafe fn aesni_hash_seeded(key: &[u8], seed: u64) -> u32 {
+    aesni_hash_in(key, seed, key.len(), &mut [0u8; 64])
+}
+
+/// `aesni_hash_seeded` padding its tail chunk in `scratch`, which must be
+/// all zero and is left all zero, and XORing `folded_len` in place of the
+/// key's length.
+#[cfg(feature = "aesni")]
+#[target_feature(enable = "aes")]
+pub(crate) unsafe fn aesni_hash_in(key: &[u8], seed: u64, folded_len: usize, scratch: &mut [u8; 64]) -> u32 {
+    let [initial, k1, k2] = crate::reference::aesni_keys(seed);
+    let mut hash = _mm_loadu_si128(initial.as_ptr() as *const _);
+    let k1 = _mm_loadu_si128(k1.as_ptr() as *const _);
+    let k2 = _mm_loadu_si128(k2.as_ptr() as *const _);
+    for chunk in key.chunks(16) {
+        let data = if chunk.len() == 16 {
+            _mm_loadu_si128(chunk.as_ptr() as *const _)
+        } else {
+            debug_assert!(chunk.len() <= 16);
+            scratch[..chunk.len()].copy_from_slice(chunk);
+            let data = _mm_loadu_si128(scratch.as_ptr() as *const _);
+            scratch[..chunk.len()].fill(0);
+            data
+        };
+
+        hash = _mm_aesenc_si128(_mm_xor_si128(hash, data), k1);
+        hash = _mm_aesenc_si128(hash, k2);
+    }
+    let len = _mm_set_epi64x(0, folded_len as i64);
+    hash = _mm_aesenc_si128(_mm_xor_si128(hash, len), k1);
+    hash = _mm_aesenclast_si128(hash, k2);
+    _mm_cvtsi128_si32(hash) as u32
+}
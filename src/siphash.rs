@@ -0,0 +1,87 @@
+// File: src/siphash.rs
+//
+// Keyed SipHash-1-3 for `ShardAlgorithm::SipHash13`. It is the hash behind
+// `std`'s `DefaultHasher`, but `std` only exposes it with random or zero
+// keys, so it is written out here (and works without `std`). With keys
+// (0, 0) it matches `DefaultHasher::new()` fed the same bytes.
+
+/// The 128-bit SipHash key derived from a config seed: the seed itself and
+/// its `fmix64` image, so seed 0 gives the zero key. Only the seed's 64 bits
+/// are secret.
+pub(crate) fn key_from_seed(seed: u64) -> (u64, u64) {
+    (seed, crate::mix::fmix64(seed))
+}
+
+pub(crate) fn siphash13(k0: u64, k1: u64, bytes: &[u8]) -> u64 {
+    let mut state = State {
+        v0: k0 ^ 0x736f_6d65_7073_6575,
+        v1: k1 ^ 0x646f_7261_6e64_6f6d,
+        v2: k0 ^ 0x6c79_6765_6e65_7261,
+        v3: k1 ^ 0x7465_6462_7974_6573,
+    };
+
+    let mut words = bytes.chunks_exact(8);
+    for word in &mut words {
+        state.compress(u64::from_le_bytes(word.try_into().unwrap()));
+    }
+    // The last block carries the tail bytes and the length's low byte.
+    let mut last = [0u8; 8];
+    last[..words.remainder().len()].copy_from_slice(words.remainder());
+    last[7] = bytes.len() as u8;
+    state.compress(u64::from_le_bytes(last));
+
+    state.v2 ^= 0xff;
+    for _ in 0..3 {
+        state.round();
+    }
+    state.v0 ^ state.v1 ^ state.v2 ^ state.v3
+}
+
+struct State {
+    v0: u64,
+    v1: u64,
+    v2: u64,
+    v3: u64,
+}
+
+impl State {
+    // One compression round per message word: the "1" in SipHash-1-3.
+    #[inline(always)]
+    fn compress(&mut self, m: u64) {
+        self.v3 ^= m;
+        self.round();
+        self.v0 ^= m;
+    }
+
+    #[inline(always)]
+    fn round(&mut self) {
+        self.v0 = self.v0.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(13) ^ self.v0;
+        self.v0 = self.v0.rotate_left(32);
+        self.v2 = self.v2.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(16) ^ self.v2;
+        self.v0 = self.v0.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(21) ^ self.v0;
+        self.v2 = self.v2.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(17) ^ self.v2;
+        self.v2 = self.v2.rotate_left(32);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zero_key_matches_default_hasher() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::Hasher;
+
+        for len in 0..=40 {
+            let bytes: Vec<u8> = (0..len as u8).map(|b| b.wrapping_mul(37)).collect();
+            let mut hasher = DefaultHasher::new();
+            hasher.write(&bytes);
+            assert_eq!(siphash13(0, 0, &bytes), hasher.finish(), "len {}", len);
+        }
+    }
+}
@@ -0,0 +1,277 @@
+// File: src/state.rs
+#[cfg(feature = "xxh3")]
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt;
+use core::hash::Hasher;
+
+use crate::{FastShard, ShardAlgorithm};
+
+/// Incremental hash state that can be forked, for tree-structured keys: hash
+/// a shared prefix once, then [`fork`](Self::fork) per leaf and write only the
+/// suffix.
+///
+/// Streams with XXH3 (default secret), or FNV-1a on builds without the `xxh3`
+/// feature. Tier selection needs the total key length up front, so the state
+/// always uses that one algorithm, and [`finish_shard`](Self::finish_shard)
+/// is a plain `hash % shard_count`. Its shards match [`FastShard::shard`]
+/// only for configs that resolve to that algorithm for the key's length and
+/// keep the default mapping: no seed, custom secret, hasher, key transform,
+/// normalization, `min_key_len` padding, finalizer, shard-count salt or
+/// weights, and [`ReductionMode::Modulo`]. [`ShardHasher`] matches `shard`
+/// for any instance but cannot fork.
+///
+/// [`ReductionMode::Modulo`]: crate::ReductionMode::Modulo
+///
+/// [`FastShard::shard`]: crate::FastShard::shard
+#[derive(Clone)]
+pub struct ShardHasherState {
+    #[cfg(feature = "xxh3")]
+    inner: xxhash_rust::xxh3::Xxh3,
+    #[cfg(not(feature = "xxh3"))]
+    inner: u64,
+}
+
+impl ShardHasherState {
+    pub fn new() -> Self {
+        Self {
+            #[cfg(feature = "xxh3")]
+            inner: xxhash_rust::xxh3::Xxh3::new(),
+            #[cfg(not(feature = "xxh3"))]
+            inner: crate::FNV_OFFSET_BASIS,
+        }
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) {
+        #[cfg(feature = "xxh3")]
+        self.inner.update(bytes);
+        #[cfg(not(feature = "xxh3"))]
+        {
+            self.inner = crate::fnv1a_64_update(self.inner, bytes);
+        }
+    }
+
+    /// A copy of the state so far, to be continued independently.
+    pub fn fork(&self) -> ShardHasherState {
+        self.clone()
+    }
+
+    /// Full hash of everything written so far.
+    pub fn finish(&self) -> u64 {
+        #[cfg(feature = "xxh3")]
+        return self.inner.digest();
+        #[cfg(not(feature = "xxh3"))]
+        return self.inner;
+    }
+
+    /// Shard of everything written so far among `shard_count` shards, by
+    /// plain modulo; see the type docs for when this matches `shard`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shard_count` is zero.
+    pub fn finish_shard(&self, shard_count: u32) -> u32 {
+        (self.finish() % shard_count as u64) as u32
+    }
+}
+
+impl Default for ShardHasherState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for ShardHasherState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShardHasherState").finish_non_exhaustive()
+    }
+}
+
+/// Incremental hasher for one key written in pieces, obtained from
+/// [`FastShard::hasher`]; [`finish`](ShardHasher::finish) returns the same
+/// shard as [`FastShard::shard`] of the concatenated pieces. When the
+/// instance hashes every key size with FNV-1a, or XXH3 without a custom
+/// secret, and has no key hooks (custom hasher, key transform,
+/// normalization or `min_key_len`), the pieces are hashed as they arrive;
+/// otherwise they are gathered, since the algorithm depends on the total
+/// length.
+///
+/// It also implements [`Hasher`], whose `finish` is the key's full 64-bit
+/// hash, [`FastShard::hash64`] of the pieces.
+pub struct ShardHasher<'a> {
+    shard: &'a FastShard,
+    stream: Stream,
+}
+
+enum Stream {
+    #[cfg(feature = "fnv")]
+    Fnv1a(u64),
+    #[cfg(feature = "xxh3")]
+    Xxh3(Box<xxhash_rust::xxh3::Xxh3>),
+    Gather(Vec<u8>),
+}
+
+impl FastShard {
+    /// A [`ShardHasher`] for a key written in pieces.
+    pub fn hasher(&self) -> ShardHasher<'_> {
+        ShardHasher {
+            shard: self,
+            stream: self.stream(),
+        }
+    }
+
+    // How `hasher` can consume pieces while still matching `shard`.
+    fn stream(&self) -> Stream {
+        let algorithm = self.get_algorithm_for_tier(None);
+        let uniform =
+            (0..self.config.tiers.len()).all(|tier| self.get_algorithm_for_tier(Some(tier)) == algorithm);
+        // An observer needs the key's length, known only at the end.
+        if !uniform || self.has_key_hooks() || self.observer.is_some() {
+            return Stream::Gather(Vec::new());
+        }
+        match algorithm {
+            #[cfg(feature = "fnv")]
+            ShardAlgorithm::Fnv1a => Stream::Fnv1a(crate::fnv1a_basis(self.config.seed)),
+            #[cfg(feature = "xxh3")]
+            ShardAlgorithm::Xxh3 if self.xxh3_secret.is_none() => {
+                Stream::Xxh3(Box::new(xxhash_rust::xxh3::Xxh3::with_seed(self.config.seed)))
+            }
+            _ => Stream::Gather(Vec::new()),
+        }
+    }
+}
+
+impl ShardHasher<'_> {
+    pub fn update(&mut self, bytes: &[u8]) {
+        match &mut self.stream {
+            #[cfg(feature = "fnv")]
+            Stream::Fnv1a(hash) => *hash = crate::fnv1a_64_update(*hash, bytes),
+            #[cfg(feature = "xxh3")]
+            Stream::Xxh3(state) => state.update(bytes),
+            Stream::Gather(key) => key.extend_from_slice(bytes),
+        }
+    }
+
+    /// The shard of everything written, recorded in the instance's metrics
+    /// like any other `shard` call.
+    pub fn finish(self) -> u32 {
+        let shard = match &self.stream {
+            Stream::Gather(key) => return self.shard.shard(key),
+            _ if self.shard.shard_count == 1 => 0,
+            _ => self.shard.reduce(Hasher::finish(&self)),
+        };
+        if let Some(metrics) = &self.shard.metrics {
+            metrics.record(shard);
+        }
+        shard
+    }
+}
+
+impl Hasher for ShardHasher<'_> {
+    fn write(&mut self, bytes: &[u8]) {
+        self.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        match &self.stream {
+            #[cfg(feature = "fnv")]
+            Stream::Fnv1a(hash) => *hash,
+            #[cfg(feature = "xxh3")]
+            Stream::Xxh3(state) => state.digest(),
+            Stream::Gather(key) => self.shard.hash64(key),
+        }
+    }
+}
+
+impl fmt::Debug for ShardHasher<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let streaming = !matches!(self.stream, Stream::Gather(_));
+        f.debug_struct("ShardHasher").field("streaming", &streaming).finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fallback_algorithm, ShardConfig};
+
+    #[test]
+    fn test_forked_prefix_matches_full_keys() {
+        let config = ShardConfig::single(fallback_algorithm());
+        let shard = FastShard::with_config(1021, config);
+
+        let prefix = b"/tenants/acme/buckets/photos/".repeat(5);
+        let mut state = ShardHasherState::new();
+        state.write(&prefix);
+
+        for i in 0..200 {
+            let suffix = format!("object-{}.jpg", i);
+            let mut leaf = state.fork();
+            leaf.write(suffix.as_bytes());
+
+            let full = [prefix.as_slice(), suffix.as_bytes()].concat();
+            let mut scratch = ShardHasherState::new();
+            scratch.write(&full);
+            assert_eq!(leaf.finish(), scratch.finish());
+            assert_eq!(leaf.finish_shard(1021), shard.shard(&full));
+        }
+        // Forks never write back into the parent.
+        assert_eq!(state.finish_shard(1021), shard.shard(&prefix));
+    }
+
+    #[test]
+    fn test_finish_shard_ignores_the_configured_mapping() {
+        let mapped = [
+            ShardConfig {
+                reduction: crate::ReductionMode::Multiply,
+                ..ShardConfig::single(fallback_algorithm())
+            },
+            ShardConfig {
+                finalizer: crate::Finalizer::Fmix64,
+                ..ShardConfig::single(fallback_algorithm())
+            },
+        ];
+        for config in mapped {
+            let shard = FastShard::with_config(1021, config);
+            let differing = (0..200u32)
+                .filter(|i| {
+                    let key = format!("object-{}", i);
+                    let mut state = ShardHasherState::new();
+                    state.write(key.as_bytes());
+                    state.finish_shard(1021) != shard.shard(key.as_bytes())
+                })
+                .count();
+            assert!(differing > 190, "{} of 200 differ", differing);
+        }
+    }
+
+    #[test]
+    fn test_shard_hasher_matches_shard_of_concatenation() {
+        let fixed = ShardConfig {
+            seed: 280,
+            ..ShardConfig::single(fallback_algorithm())
+        };
+        let shards = [
+            FastShard::with_config(1021, fixed),
+            FastShard::new(1021),
+            FastShard::new(1021).with_key_transform(|key| key.to_ascii_lowercase().into()),
+            FastShard::new(1),
+        ];
+        for shard in &shards {
+            let mut hasher = shard.hasher();
+            hasher.update(b"foo");
+            hasher.update(b"bar");
+            assert_eq!(Hasher::finish(&hasher), shard.hash64(b"foobar"));
+            assert_eq!(hasher.finish(), shard.shard(b"foobar"));
+
+            let pieces: [&[u8]; 4] = [b"tenant-7/", b"", b"orders/", &[0xff; 100]];
+            let mut hasher = shard.hasher();
+            for piece in pieces {
+                Hasher::write(&mut hasher, piece);
+            }
+            assert_eq!(hasher.finish(), shard.shard(&pieces.concat()));
+        }
+        assert!(format!("{:?}", shards[0].hasher()).contains("streaming: true"));
+        assert!(format!("{:?}", shards[2].hasher()).contains("streaming: false"));
+    }
+}
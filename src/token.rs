@@ -0,0 +1,55 @@
+// File: src/token.rs
+use crate::FastShard;
+
+/// A key's full hash, computed once and passed around in place of the key.
+/// It does not depend on the shard count; [`FastShard::shard_token`] applies
+/// the count when the shard is finally needed, so a token stays valid across
+/// resharding as long as the config (algorithms, secret, hooks) is the same.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ShardToken(pub u64);
+
+impl FastShard {
+    /// The key's full 64-bit [`hash64`](Self::hash64) as a token, taken
+    /// before the finalizer, shard-count salt or any count is applied. It
+    /// maps to the key's shard for as long as the config, algorithms, secret
+    /// and hooks stay the same; see [`ShardToken`].
+    pub fn tokenize(&self, key: &[u8]) -> ShardToken {
+        ShardToken(self.hash64(key))
+    }
+
+    /// The shard for a token from [`tokenize`](Self::tokenize); equal to
+    /// `shard(key)` for the key it was made from.
+    pub fn shard_token(&self, token: ShardToken) -> u32 {
+        let shard = self.reduce(token.0);
+        // The key is gone by now, so only the metrics see this placement.
+        if let Some(metrics) = &self.metrics {
+            metrics.record(shard);
+        }
+        shard
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ShardConfig;
+
+    #[test]
+    fn test_token_maps_like_shard_across_counts() {
+        for salted in [false, true] {
+            let config = ShardConfig {
+                salt_with_shard_count: salted,
+                ..ShardConfig::default()
+            };
+            let small = FastShard::with_config(16, config.clone());
+            let large = FastShard::with_config(1021, config);
+            for i in 0..500u32 {
+                let key = format!("route-{}", i);
+                let token = small.tokenize(key.as_bytes());
+                assert_eq!(token, large.tokenize(key.as_bytes()));
+                assert_eq!(small.shard_token(token), small.shard(key.as_bytes()));
+                assert_eq!(large.shard_token(token), large.shard(key.as_bytes()));
+            }
+        }
+    }
+}
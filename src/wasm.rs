@@ -0,0 +1,63 @@
+// File: src/wasm.rs
+//
+// WebAssembly SIMD128 kernel. WASM has no runtime feature detection: a
+// module either validates with SIMD instructions or not at all, so this is
+// compiled only when the build enables `simd128` as a target feature
+// (`-C target-feature=+simd128`) and the `simd128` cargo feature is on.
+//
+// It computes the AVX-512 hash bit for bit, four 128-bit vectors standing in
+// for one 512-bit one: each 64-byte chunk's sixteen weighted lanes are summed
+// and folded exactly as in `reference::avx512_hash_seeded`, its scalar
+// model, so the two kernels place keys identically.
+#![allow(dead_code)]
+
+use core::arch::wasm32::*;
+
+use crate::reference::{chunk_finish, chunk_fold, chunk_initial, AVX512_LANE_WEIGHTS};
+
+pub(crate) fn simd128_hash(key: &[u8]) -> u32 {
+    simd128_hash_in(key, 0, key.len(), &mut [0u8; 64])
+}
+
+pub(crate) fn simd128_hash_seeded(key: &[u8], seed: u64) -> u32 {
+    simd128_hash_in(key, seed, key.len(), &mut [0u8; 64])
+}
+
+/// `simd128_hash_seeded` padding its tail chunk in `scratch`, which must be
+/// all zero and is left all zero, and folding `folded_len` in place of the
+/// key's length.
+pub(crate) fn simd128_hash_in(key: &[u8], seed: u64, folded_len: usize, scratch: &mut [u8; 64]) -> u32 {
+    let w = AVX512_LANE_WEIGHTS;
+    let weights = [0, 4, 8, 12].map(|lane| u32x4(w[lane], w[lane + 1], w[lane + 2], w[lane + 3]));
+    let mut hash = chunk_initial(seed);
+    for (index, chunk) in key.chunks(64).enumerate() {
+        let lane_sum = if chunk.len() == 64 {
+            weighted_sum(chunk, &weights)
+        } else {
+            debug_assert!(chunk.len() <= 64);
+            scratch[..chunk.len()].copy_from_slice(chunk);
+            let sum = weighted_sum(scratch, &weights);
+            scratch[..chunk.len()].fill(0);
+            sum
+        };
+        hash = chunk_fold(hash, index, lane_sum);
+    }
+    chunk_finish(hash, folded_len)
+}
+
+// Sum of the sixteen little-endian 32-bit lanes of a 64-byte chunk, each
+// times its weight. WASM is little-endian, like the scalar model's reads.
+#[inline(always)]
+fn weighted_sum(chunk: &[u8], weights: &[v128; 4]) -> u32 {
+    debug_assert_eq!(chunk.len(), 64);
+    let mut acc = u32x4_splat(0);
+    for (quarter, weight) in weights.iter().enumerate() {
+        // In bounds: `chunk` holds 64 bytes; WASM loads need no alignment.
+        let lanes = unsafe { v128_load(chunk.as_ptr().add(quarter * 16) as *const v128) };
+        acc = i32x4_add(acc, i32x4_mul(lanes, *weight));
+    }
+    u32x4_extract_lane::<0>(acc)
+        .wrapping_add(u32x4_extract_lane::<1>(acc))
+        .wrapping_add(u32x4_extract_lane::<2>(acc))
+        .wrapping_add(u32x4_extract_lane::<3>(acc))
+}